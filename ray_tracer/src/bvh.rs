@@ -0,0 +1,778 @@
+use crate::geometry::{axis::Axis3, bounds::Bounds3, point, vector};
+use crate::number;
+use crate::ray::Ray;
+use cgmath::Point3;
+use rayon::prelude::*;
+
+/// The maximum number of primitives that are stored together in a leaf node.
+const MAX_PRIMITIVES_IN_LEAF: usize = 4;
+
+/// The number of buckets that primitive centroids are binned into when
+/// evaluating the surface area heuristic for a node.
+const BUCKET_COUNT: usize = 12;
+
+/// Nodes with fewer primitives than this are built on the current thread.
+/// Splitting smaller nodes across threads costs more in scheduling overhead
+/// than it saves.
+const PARALLEL_BUILD_THRESHOLD: usize = 4096;
+
+/// The relative cost of traversing an interior node, compared to the cost of
+/// intersecting a ray with a single primitive, or a single group of
+/// primitives.
+const TRAVERSAL_COST: f32 = 0.125;
+
+/// A shape that can be enclosed by an axis-aligned bounding box.
+pub trait Bounded {
+    /// Return a bounding box around the shape in world space.
+    fn world_bounds(&self) -> Bounds3<f32>;
+}
+
+/// A bounding volume hierarchy that is built using the surface area heuristic
+/// (SAH). See section 4.3 of PBR ed. 3.
+///
+/// The hierarchy owns its primitives and stores them in the order in which they
+/// are referenced by its leaves, so that each leaf refers to a contiguous range
+/// of primitives.
+pub struct Bvh<P> {
+    primitives: Vec<P>,
+
+    /// The nodes of the hierarchy in depth-first order. The first child of an
+    /// interior node immediately follows it.
+    nodes: Vec<LinearNode>,
+}
+
+impl<P: Bounded + Send + Sync> Bvh<P> {
+    /// Build a hierarchy around the primitives.
+    ///
+    /// Large subtrees are built in parallel on the rayon thread pool, and
+    /// primitives are binned into `BUCKET_COUNT` buckets along the longest
+    /// axis of their centroids' bounds to find the split with the lowest
+    /// estimated cost.
+    pub fn build(primitives: Vec<P>) -> Self {
+        Self::build_for_groups(primitives, 1)
+    }
+
+    /// Build a hierarchy whose leaves will be grouped with `group_leaves`.
+    ///
+    /// The surface area heuristic then estimates the cost of a leaf by the
+    /// number of groups it will hold, rather than the number of primitives,
+    /// since testing a group costs about as much as testing one primitive.
+    /// This favors leaves that fill their groups.
+    ///
+    /// * primitives - The primitives to build the hierarchy around.
+    /// * group_size - The `max_group_size` that will be passed to
+    ///   `group_leaves`.
+    pub fn build_for_groups(primitives: Vec<P>, group_size: usize) -> Self {
+        if primitives.is_empty() {
+            return Self {
+                primitives,
+                nodes: vec![],
+            };
+        }
+
+        let mut infos: Vec<PrimitiveInfo> = primitives
+            .par_iter()
+            .enumerate()
+            .map(|(index, primitive)| PrimitiveInfo::new(index, primitive.world_bounds()))
+            .collect();
+        let root = build_recursive(&mut infos, 0, group_size);
+
+        let mut nodes = Vec::with_capacity(root.node_count());
+        flatten(root, &mut nodes);
+
+        // Reorder the primitives to match the order of the leaves.
+        let mut slots: Vec<Option<P>> = primitives.into_iter().map(Some).collect();
+        let primitives = infos
+            .iter()
+            .map(|info| slots[info.primitive_index].take().unwrap())
+            .collect();
+
+        Self { primitives, nodes }
+    }
+}
+
+impl<P> Bvh<P> {
+    /// Return a bounding box around all of the primitives in the hierarchy, or
+    /// `None` if the hierarchy is empty.
+    pub fn world_bounds(&self) -> Option<Bounds3<f32>> {
+        self.nodes.first().map(|root| root.bounds)
+    }
+
+    /// Return the primitives in the hierarchy, in the order in which the
+    /// hierarchy's leaves store them.
+    pub fn primitives(&self) -> &[P] {
+        &self.primitives
+    }
+
+    /// Convert the hierarchy into one with the same nodes, in which the
+    /// primitives of each leaf are grouped into larger primitives. This lets
+    /// the primitives of a leaf be tested together, e.g. with SIMD
+    /// instructions.
+    ///
+    /// * max_group_size - The maximum number of primitives in a group. A leaf
+    ///   with more primitives than this is split into several groups.
+    /// * group - Combines up to `max_group_size` consecutive primitives of a
+    ///   leaf into one.
+    pub fn group_leaves<Q>(self, max_group_size: usize, group: impl Fn(&[P]) -> Q) -> Bvh<Q> {
+        let mut groups = Vec::with_capacity(self.primitives.len());
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|node| match node.kind {
+                LinearNodeKind::Leaf {
+                    first_primitive_index,
+                    primitive_count,
+                } => {
+                    let range = first_primitive_index..first_primitive_index + primitive_count;
+                    let first_group_index = groups.len();
+                    groups.extend(self.primitives[range].chunks(max_group_size).map(&group));
+                    LinearNode {
+                        bounds: node.bounds,
+                        kind: LinearNodeKind::Leaf {
+                            first_primitive_index: first_group_index,
+                            primitive_count: groups.len() - first_group_index,
+                        },
+                    }
+                }
+                LinearNodeKind::Interior { .. } => *node,
+            })
+            .collect();
+
+        Bvh {
+            primitives: groups,
+            nodes,
+        }
+    }
+
+    /// Find the closest intersection between the ray and a primitive in the
+    /// hierarchy.
+    ///
+    /// * ray - The ray to trace.
+    /// * intersect - Tests the ray against a single primitive. It should
+    ///   return the parametric value of the intersection, along with any other
+    ///   information about the intersection, if the primitive is hit in the
+    ///   (0, `ray.t_max`) range.
+    pub fn intersect<T>(
+        &self,
+        ray: &Ray,
+        intersect: impl Fn(&P, &Ray) -> Option<(f32, T)>,
+    ) -> Option<(f32, T)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut ray = *ray;
+        let inv_direction = ray.direction.map(|c| 1.0 / c);
+        let direction_is_negative = [
+            inv_direction.x < 0.0,
+            inv_direction.y < 0.0,
+            inv_direction.z < 0.0,
+        ];
+
+        let mut closest = None;
+        let mut to_visit = Vec::with_capacity(64);
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounds.intersect_p(&ray, &inv_direction, &direction_is_negative) {
+                match node.kind {
+                    LinearNodeKind::Leaf {
+                        first_primitive_index,
+                        primitive_count,
+                    } => {
+                        let range = first_primitive_index..first_primitive_index + primitive_count;
+                        for primitive in &self.primitives[range] {
+                            if let Some((t, hit)) = intersect(primitive, &ray) {
+                                // Shorten the ray so that primitives farther
+                                // away than this hit are skipped.
+                                ray.t_max = t;
+                                closest = Some((t, hit));
+                            }
+                        }
+                        match to_visit.pop() {
+                            Some(next) => current = next,
+                            None => break,
+                        }
+                    }
+                    LinearNodeKind::Interior {
+                        second_child_index,
+                        axis,
+                    } => {
+                        // Visit the near child first, since a hit in the near
+                        // child lets us skip more of the far child.
+                        if direction_is_negative[axis as usize] {
+                            to_visit.push(current + 1);
+                            current = second_child_index;
+                        } else {
+                            to_visit.push(second_child_index);
+                            current += 1;
+                        }
+                    }
+                }
+            } else {
+                match to_visit.pop() {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Find the closest intersection between each ray in a packet and a
+    /// primitive in the hierarchy. The result for each ray is the same as
+    /// `intersect` would return for it.
+    ///
+    /// Coherent rays that share a direction, such as the primary rays of an
+    /// orthographic camera, are traced together: each node is tested once
+    /// against the whole packet, and only the nodes that some ray might hit are
+    /// visited. Rays with different directions are traced one at a time.
+    ///
+    /// * rays - The rays to trace.
+    /// * intersect - Tests a ray against a single primitive, as in `intersect`.
+    pub fn intersect_packet<T>(
+        &self,
+        rays: &[Ray],
+        intersect: impl Fn(&P, &Ray) -> Option<(f32, T)>,
+    ) -> Vec<Option<(f32, T)>> {
+        let direction = match rays.first() {
+            Some(ray) => ray.direction,
+            None => return vec![],
+        };
+        if self.nodes.is_empty() || rays.iter().any(|ray| ray.direction != direction) {
+            return rays.iter().map(|ray| self.intersect(ray, &intersect)).collect();
+        }
+
+        let mut rays = rays.to_vec();
+        let inv_direction = direction.map(|c| 1.0 / c);
+        let direction_is_negative = [
+            inv_direction.x < 0.0,
+            inv_direction.y < 0.0,
+            inv_direction.z < 0.0,
+        ];
+        let origins: Vec<Point3<f32>> = rays.iter().map(|ray| ray.origin).collect();
+        let origin_bounds = Bounds3::from_points(&origins);
+        let packet_t_max = |rays: &[Ray]| rays.iter().fold(0.0, |t_max, ray| ray.t_max.max(t_max));
+        let mut t_max = packet_t_max(&rays);
+
+        let mut closest: Vec<Option<(f32, T)>> = rays.iter().map(|_| None).collect();
+        let mut to_visit = Vec::with_capacity(64);
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounds.intersect_p_parallel_rays(
+                &origin_bounds,
+                t_max,
+                &inv_direction,
+                &direction_is_negative,
+            ) {
+                match node.kind {
+                    LinearNodeKind::Leaf {
+                        first_primitive_index,
+                        primitive_count,
+                    } => {
+                        let range = first_primitive_index..first_primitive_index + primitive_count;
+                        for (ray, closest) in rays.iter_mut().zip(closest.iter_mut()) {
+                            if !node.bounds.intersect_p(ray, &inv_direction, &direction_is_negative)
+                            {
+                                continue;
+                            }
+                            for primitive in &self.primitives[range.clone()] {
+                                if let Some((t, hit)) = intersect(primitive, ray) {
+                                    ray.t_max = t;
+                                    *closest = Some((t, hit));
+                                }
+                            }
+                        }
+                        t_max = packet_t_max(&rays);
+                        match to_visit.pop() {
+                            Some(next) => current = next,
+                            None => break,
+                        }
+                    }
+                    LinearNodeKind::Interior {
+                        second_child_index,
+                        axis,
+                    } => {
+                        // The rays share a direction, so visiting the near
+                        // child first visits nodes in the same order as
+                        // `intersect` does for each ray.
+                        if direction_is_negative[axis as usize] {
+                            to_visit.push(current + 1);
+                            current = second_child_index;
+                        } else {
+                            to_visit.push(second_child_index);
+                            current += 1;
+                        }
+                    }
+                }
+            } else {
+                match to_visit.pop() {
+                    Some(next) => current = next,
+                    None => break,
+                }
+            }
+        }
+
+        closest
+    }
+}
+
+/// A node of the hierarchy as it is stored for traversal.
+#[derive(Debug, Clone, Copy)]
+struct LinearNode {
+    bounds: Bounds3<f32>,
+    kind: LinearNodeKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LinearNodeKind {
+    Leaf {
+        first_primitive_index: usize,
+        primitive_count: usize,
+    },
+    Interior {
+        second_child_index: usize,
+
+        /// The axis along which primitives were partitioned between the two
+        /// children.
+        axis: Axis3,
+    },
+}
+
+/// A node of the hierarchy as it is represented during construction.
+enum BuildNode {
+    Leaf {
+        bounds: Bounds3<f32>,
+        first_primitive_index: usize,
+        primitive_count: usize,
+    },
+    Interior {
+        bounds: Bounds3<f32>,
+        children: Box<(BuildNode, BuildNode)>,
+        axis: Axis3,
+    },
+}
+
+impl BuildNode {
+    fn node_count(&self) -> usize {
+        match self {
+            BuildNode::Leaf { .. } => 1,
+            BuildNode::Interior { children, .. } => {
+                1 + children.0.node_count() + children.1.node_count()
+            }
+        }
+    }
+}
+
+/// The information about a primitive that is needed to build the hierarchy.
+#[derive(Debug, Clone, Copy)]
+struct PrimitiveInfo {
+    primitive_index: usize,
+    bounds: Bounds3<f32>,
+    centroid: Point3<f32>,
+}
+
+impl PrimitiveInfo {
+    fn new(primitive_index: usize, bounds: Bounds3<f32>) -> Self {
+        Self {
+            primitive_index,
+            bounds,
+            centroid: bounds.centroid(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    count: usize,
+    bounds: Bounds3<f32>,
+}
+
+impl Default for Bucket {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            bounds: Bounds3::empty(),
+        }
+    }
+}
+
+/// Build the subtree for the given primitives, partitioning `infos` in place
+/// so that each leaf refers to a contiguous range of it.
+///
+/// * infos - The primitives in the subtree.
+/// * offset - The index of `infos[0]` in the full list of primitives.
+/// * group_size - The number of primitives that will be tested together.
+fn build_recursive(infos: &mut [PrimitiveInfo], offset: usize, group_size: usize) -> BuildNode {
+    let parallel = infos.len() >= PARALLEL_BUILD_THRESHOLD;
+    let (bounds, centroid_bounds) = if parallel {
+        infos
+            .par_iter()
+            .fold(
+                || (Bounds3::empty(), Bounds3::empty()),
+                |(bounds, centroid_bounds), info| {
+                    (
+                        bounds.union(&info.bounds),
+                        centroid_bounds.union_point(&info.centroid),
+                    )
+                },
+            )
+            .reduce(
+                || (Bounds3::empty(), Bounds3::empty()),
+                |(b1, c1), (b2, c2)| (b1.union(&b2), c1.union(&c2)),
+            )
+    } else {
+        infos.iter().fold(
+            (Bounds3::empty(), Bounds3::empty()),
+            |(bounds, centroid_bounds), info| {
+                (
+                    bounds.union(&info.bounds),
+                    centroid_bounds.union_point(&info.centroid),
+                )
+            },
+        )
+    };
+
+    let leaf = BuildNode::Leaf {
+        bounds,
+        first_primitive_index: offset,
+        primitive_count: infos.len(),
+    };
+    if infos.len() == 1 {
+        return leaf;
+    }
+
+    // If all of the centroids are at the same position, there's no way to
+    // partition the primitives along this axis.
+    let axis = centroid_bounds.maximum_extent();
+    if point::component(centroid_bounds.max, axis) == point::component(centroid_bounds.min, axis)
+    {
+        return leaf;
+    }
+
+    let split = if infos.len() <= 2 {
+        // For a couple of primitives, the SAH isn't worth computing. Split
+        // them into two equally sized halves instead.
+        let mid = infos.len() / 2;
+        infos.select_nth_unstable_by(mid, |a, b| {
+            number::f32::total_cmp(
+                &point::component(a.centroid, axis),
+                &point::component(b.centroid, axis),
+            )
+        });
+        mid
+    } else {
+        match sah_split(infos, &bounds, &centroid_bounds, axis, parallel, group_size) {
+            Some(split) => split,
+            None => return leaf,
+        }
+    };
+
+    let (below, above) = infos.split_at_mut(split);
+    let children = if parallel {
+        rayon::join(
+            || build_recursive(below, offset, group_size),
+            || build_recursive(above, offset + split, group_size),
+        )
+    } else {
+        (
+            build_recursive(below, offset, group_size),
+            build_recursive(above, offset + split, group_size),
+        )
+    };
+
+    BuildNode::Interior {
+        bounds,
+        children: Box::new(children),
+        axis,
+    }
+}
+
+/// Partition the primitives at the bucket boundary that has the lowest
+/// estimated cost according to the surface area heuristic, and return the
+/// index of the first primitive above the boundary.
+///
+/// Returns `None` if it's cheaper to put all the primitives in a leaf.
+fn sah_split(
+    infos: &mut [PrimitiveInfo],
+    bounds: &Bounds3<f32>,
+    centroid_bounds: &Bounds3<f32>,
+    axis: Axis3,
+    parallel: bool,
+    group_size: usize,
+) -> Option<usize> {
+    let bucket_index = |info: &PrimitiveInfo| {
+        let offset = vector::component(centroid_bounds.offset(&info.centroid), axis);
+        ((BUCKET_COUNT as f32 * offset) as usize).min(BUCKET_COUNT - 1)
+    };
+    let add_to_buckets = |mut buckets: [Bucket; BUCKET_COUNT], info: &PrimitiveInfo| {
+        let bucket = &mut buckets[bucket_index(info)];
+        bucket.count += 1;
+        bucket.bounds = bucket.bounds.union(&info.bounds);
+        buckets
+    };
+    let buckets = if parallel {
+        infos
+            .par_iter()
+            .fold(|| [Bucket::default(); BUCKET_COUNT], add_to_buckets)
+            .reduce(
+                || [Bucket::default(); BUCKET_COUNT],
+                |mut b1, b2| {
+                    for (left, right) in b1.iter_mut().zip(&b2) {
+                        left.count += right.count;
+                        left.bounds = left.bounds.union(&right.bounds);
+                    }
+                    b1
+                },
+            )
+    } else {
+        infos
+            .iter()
+            .fold([Bucket::default(); BUCKET_COUNT], add_to_buckets)
+    };
+
+    // Estimate the cost of splitting after each bucket, and keep the cheapest.
+    // Sweeping from both ends lets us accumulate the counts and bounds on each
+    // side of every split in a single pass per side.
+    let mut costs = [0.0; BUCKET_COUNT - 1];
+    let mut count_below = 0;
+    let mut bounds_below = Bounds3::empty();
+    for (cost, bucket) in costs.iter_mut().zip(&buckets) {
+        count_below += bucket.count;
+        bounds_below = bounds_below.union(&bucket.bounds);
+        *cost = count_below.div_ceil(group_size) as f32 * bounds_below.surface_area();
+    }
+    let mut count_above = 0;
+    let mut bounds_above = Bounds3::empty();
+    for (cost, bucket) in costs.iter_mut().zip(&buckets[1..]).rev() {
+        count_above += bucket.count;
+        bounds_above = bounds_above.union(&bucket.bounds);
+        *cost += count_above.div_ceil(group_size) as f32 * bounds_above.surface_area();
+    }
+    let (min_cost_bucket, min_cost) = costs
+        .iter()
+        .map(|cost| TRAVERSAL_COST + cost / bounds.surface_area())
+        .enumerate()
+        .min_by(|(_, c1), (_, c2)| number::f32::total_cmp(c1, c2))?;
+
+    let leaf_cost = infos.len().div_ceil(group_size) as f32;
+    let max_primitives_in_leaf = MAX_PRIMITIVES_IN_LEAF.max(group_size);
+    if infos.len() <= max_primitives_in_leaf && min_cost >= leaf_cost {
+        return None;
+    }
+
+    let split = partition(infos, |info| bucket_index(info) <= min_cost_bucket);
+    if split == 0 || split == infos.len() {
+        // This can only happen if the bucket boundaries are degenerate.
+        None
+    } else {
+        Some(split)
+    }
+}
+
+/// Reorder the slice so that every element for which `predicate` returns
+/// `true` precedes every element for which it returns `false`. Return the
+/// number of elements for which `predicate` returned `true`.
+fn partition<T>(slice: &mut [T], predicate: impl Fn(&T) -> bool) -> usize {
+    let mut first_false = 0;
+    for i in 0..slice.len() {
+        if predicate(&slice[i]) {
+            slice.swap(first_false, i);
+            first_false += 1;
+        }
+    }
+    first_false
+}
+
+/// Append the subtree to `nodes` in depth-first order.
+fn flatten(node: BuildNode, nodes: &mut Vec<LinearNode>) {
+    match node {
+        BuildNode::Leaf {
+            bounds,
+            first_primitive_index,
+            primitive_count,
+        } => nodes.push(LinearNode {
+            bounds,
+            kind: LinearNodeKind::Leaf {
+                first_primitive_index,
+                primitive_count,
+            },
+        }),
+        BuildNode::Interior {
+            bounds,
+            children,
+            axis,
+        } => {
+            let index = nodes.len();
+            // The index of the second child isn't known until the first
+            // child's subtree has been flattened, so push a placeholder.
+            nodes.push(LinearNode {
+                bounds,
+                kind: LinearNodeKind::Interior {
+                    second_child_index: 0,
+                    axis,
+                },
+            });
+            let (first, second) = *children;
+            flatten(first, nodes);
+            let second_child_index = nodes.len();
+            nodes[index].kind = LinearNodeKind::Interior {
+                second_child_index,
+                axis,
+            };
+            flatten(second, nodes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod bvh_tests {
+    use super::{Bounded, Bvh};
+    use crate::geometry::bounds::Bounds3;
+    use crate::ray::Ray;
+    use cgmath::{InnerSpace, Point3, Vector3};
+
+    /// A sphere is simple enough to intersect that it makes it easy to check
+    /// traversal against a brute force search.
+    #[derive(Debug, Clone, Copy)]
+    struct Sphere {
+        id: usize,
+        center: Point3<f32>,
+        radius: f32,
+    }
+
+    impl Bounded for Sphere {
+        fn world_bounds(&self) -> Bounds3<f32> {
+            let r = Vector3::new(self.radius, self.radius, self.radius);
+            Bounds3::new(self.center - r, self.center + r)
+        }
+    }
+
+    impl Sphere {
+        fn intersect(&self, ray: &Ray) -> Option<(f32, usize)> {
+            let oc = ray.origin - self.center;
+            let a = ray.direction.magnitude2();
+            let b = 2.0 * oc.dot(ray.direction);
+            let c = oc.magnitude2() - self.radius * self.radius;
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let t = (-b - discriminant.sqrt()) / (2.0 * a);
+            if t > 0.0 && t < ray.t_max {
+                Some((t, self.id))
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Return a grid of spheres large enough to exercise the parallel build.
+    fn sphere_grid(n: usize) -> Vec<Sphere> {
+        (0..n * n * n)
+            .map(|id| Sphere {
+                id,
+                center: Point3::new(
+                    (id % n) as f32,
+                    ((id / n) % n) as f32,
+                    (id / (n * n)) as f32,
+                ),
+                radius: 0.3 + 0.1 * ((id * 7) % 3) as f32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_hierarchy() {
+        let bvh: Bvh<Sphere> = Bvh::build(vec![]);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), f32::MAX);
+        assert!(bvh.world_bounds().is_none());
+        assert!(bvh.intersect(&ray, |s, r| s.intersect(r)).is_none());
+    }
+
+    #[test]
+    fn keeps_every_primitive() {
+        let spheres = sphere_grid(20);
+        let bvh = Bvh::build(spheres.clone());
+        let mut ids: Vec<usize> = bvh.primitives().iter().map(|s| s.id).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, (0..spheres.len()).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_brute_force() {
+        let spheres = sphere_grid(20);
+        let bvh = Bvh::build(spheres.clone());
+
+        for i in 0..200 {
+            let origin = Point3::new(-5.0, (i % 20) as f32 * 0.97, (i / 10) as f32 * 0.91);
+            let direction = Vector3::new(1.0, 0.05 * (i % 7) as f32, -0.03 * (i % 5) as f32);
+            let ray = Ray::new(origin, direction, f32::MAX);
+
+            let expected = spheres
+                .iter()
+                .filter_map(|s| s.intersect(&ray))
+                .min_by(|(t1, _), (t2, _)| t1.partial_cmp(t2).unwrap())
+                .map(|(_, id)| id);
+            let actual = bvh.intersect(&ray, |s, r| s.intersect(r)).map(|(_, id)| id);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn grouping_leaves_keeps_the_same_hits() {
+        let spheres = sphere_grid(12);
+        let bvh = Bvh::build(spheres.clone());
+        let grouped = Bvh::build_for_groups(spheres, 3).group_leaves(3, |group| group.to_vec());
+
+        for i in 0..100 {
+            let origin = Point3::new(-5.0, (i % 12) as f32 * 0.93, (i / 10) as f32 * 1.07);
+            let direction = Vector3::new(1.0, 0.04 * (i % 5) as f32, -0.02 * (i % 7) as f32);
+            let ray = Ray::new(origin, direction, f32::MAX);
+
+            let expected = bvh.intersect(&ray, |s, r| s.intersect(r)).map(|(_, id)| id);
+            let actual = grouped
+                .intersect(&ray, |group, r| {
+                    group
+                        .iter()
+                        .filter_map(|s| s.intersect(r))
+                        .min_by(|(t1, _), (t2, _)| t1.partial_cmp(t2).unwrap())
+                })
+                .map(|(_, id)| id);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn packets_find_the_same_hits_as_single_rays() {
+        let spheres = sphere_grid(12);
+        let bvh = Bvh::build(spheres);
+
+        let direction = Vector3::new(1.0, 0.1, -0.05);
+        let parallel: Vec<Ray> = (0..64)
+            .map(|i| {
+                let origin = Point3::new(-5.0, (i % 8) as f32 * 1.3, (i / 8) as f32 * 1.4);
+                Ray::new(origin, direction, f32::MAX)
+            })
+            .collect();
+        let mut mixed = parallel.clone();
+        mixed[7].direction = Vector3::new(1.0, -0.2, 0.1);
+
+        for rays in [parallel, mixed] {
+            let expected: Vec<Option<usize>> = rays
+                .iter()
+                .map(|ray| bvh.intersect(ray, |s, r| s.intersect(r)).map(|(_, id)| id))
+                .collect();
+            let actual: Vec<Option<usize>> = bvh
+                .intersect_packet(&rays, |s, r| s.intersect(r))
+                .into_iter()
+                .map(|hit| hit.map(|(_, id)| id))
+                .collect();
+            assert_eq!(actual, expected);
+            assert!(expected.iter().any(Option::is_some));
+            assert!(expected.iter().any(Option::is_none));
+        }
+    }
+}
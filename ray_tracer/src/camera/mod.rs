@@ -3,7 +3,10 @@ mod sample;
 
 pub use {orthographic::OrthographicCamera, sample::CameraSample};
 
-use crate::ray::{Ray, RayDifferential};
+use crate::{
+    geometry::bounds::{Bounds2, Bounds3},
+    ray::{Ray, RayDifferential},
+};
 
 pub trait Camera {
     /// Generate a ray for the given sample.
@@ -29,4 +32,14 @@ pub trait Camera {
         &self,
         sample: &CameraSample,
     ) -> (Ray, Option<RayDifferential>, f32);
+
+    /// Return a bounding box in raster space around the film points whose
+    /// rays could pass through the given world space bounding box, or `None`
+    /// if the camera can't bound them.
+    ///
+    /// Samples taken outside of the returned bounds can't see anything inside
+    /// `world_bounds`, so renderers may skip tracing their rays.
+    fn raster_bounds(&self, _world_bounds: &Bounds3<f32>) -> Option<Bounds2<f32>> {
+        None
+    }
 }
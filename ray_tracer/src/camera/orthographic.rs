@@ -1,6 +1,6 @@
 use super::{Camera, CameraSample};
 use crate::{
-    geometry::bounds::Bounds2,
+    geometry::bounds::{Bounds2, Bounds3},
     ray::{Ray, RayDifferential},
 };
 use cgmath::{Matrix4, Point2, Point3, Transform, Vector2, Vector3};
@@ -63,17 +63,30 @@ impl OrthographicCamera {
         }
     }
 
+    /// Returns the matrix that transforms world space to raster space.
+    ///
+    /// The z axis of raster space points in the camera's viewing direction,
+    /// with the near clipping plane at z = 0, so points on rays generated by
+    /// the camera are ordered by their raster space z values.
+    pub fn world_to_raster(&self) -> Matrix4<f32> {
+        (self.camera_to_world * self.raster_to_camera)
+            .inverse_transform()
+            .unwrap()
+    }
+
     /// Returns the projective orthographic matrix that transforms camera space
     /// to screen space.
     ///
     /// * `z_near` - z value of the near clipping plane in camera space.
     /// * `z_far` - z value of the far clipping plane in camera space.
+    #[allow(clippy::neg_multiply)]
     fn camera_to_screen(z_near: f32, z_far: f32) -> Matrix4<f32> {
         Matrix4::from_nonuniform_scale(1.0, 1.0, 1.0 / (z_far - z_near))
             * Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0 * z_near))
     }
 
     /// Returns the matrix that transforms camera screen space to raster space.
+    #[allow(clippy::neg_multiply)]
     fn screen_to_raster(screen_bounds: Bounds2<f32>, resolution: Vector2<usize>) -> Matrix4<f32> {
         Matrix4::from_nonuniform_scale(resolution.x as f32, resolution.y as f32, 1.0)
             * Matrix4::from_nonuniform_scale(
@@ -125,6 +138,22 @@ impl Camera for OrthographicCamera {
 
         (world_primay_ray, Some(world_ray_differential), 1.0)
     }
+
+    fn raster_bounds(&self, world_bounds: &Bounds3<f32>) -> Option<Bounds2<f32>> {
+        // Orthographic rays travel straight along the raster space z axis, so
+        // the film points whose rays pass through the box are inside the box's
+        // projection onto the film. The projection is padded by a pixel to
+        // cover floating-point error in the transformations.
+        let world_to_raster = self.world_to_raster();
+        let projection = world_bounds
+            .corners()
+            .iter()
+            .map(|corner| world_to_raster.transform_point(*corner))
+            .fold(Bounds2::empty(), |bounds, p| {
+                bounds.union_point(&Point2::new(p.x, p.y))
+            });
+        Some(projection.padded(1.0, 1.0))
+    }
 }
 
 #[cfg(test)]
@@ -225,3 +254,30 @@ mod generate_ray_tests {
         Matrix4::from_scale(S::one())
     }
 }
+
+#[cfg(test)]
+mod raster_bounds_tests {
+    use crate::{
+        camera::{Camera, OrthographicCamera},
+        geometry::bounds::Bounds3,
+    };
+    use cgmath::{Matrix4, Point3, Vector2};
+
+    #[test]
+    fn padded_projection_of_box() {
+        let camera = OrthographicCamera::new(
+            Matrix4::from_scale(1.0),
+            0.0,
+            100.0,
+            Vector2::new(4.0, 2.0),
+            Vector2::new(400, 200),
+        );
+        let world_bounds = Bounds3::new(Point3::new(-0.5, -0.25, 1.0), Point3::new(0.5, 0.25, 2.0));
+
+        let raster_bounds = camera.raster_bounds(&world_bounds).unwrap();
+        assert!((raster_bounds.min.x - 149.0).abs() < 1e-3);
+        assert!((raster_bounds.min.y - 74.0).abs() < 1e-3);
+        assert!((raster_bounds.max.x - 251.0).abs() < 1e-3);
+        assert!((raster_bounds.max.y - 126.0).abs() < 1e-3);
+    }
+}
@@ -112,6 +112,36 @@ impl RgbaSpectrum {
     pub fn has_nan(&self) -> bool {
         self.samples.iter().any(|s| s.is_nan())
     }
+
+    /// Return the luminance of the spectrum, which is its Y component in the
+    /// XYZ color space.
+    pub fn luminance(&self) -> f32 {
+        Xyza::from(*self).y()
+    }
+
+    /// Return the spectrum with the sRGB transfer function applied to its red,
+    /// green and blue samples, which are assumed to be linear and in [0, 1].
+    /// Alpha is left unchanged, since it's linear in every color space.
+    pub fn to_srgb(&self) -> Self {
+        let encode = |c: f32| {
+            if c <= 0.0031308 {
+                12.92 * c
+            } else {
+                1.055 * c.powf(1.0 / 2.4) - 0.055
+            }
+        };
+        Self::from_rgba(
+            encode(self.r()),
+            encode(self.g()),
+            encode(self.b()),
+            self.a(),
+        )
+    }
+
+    /// Return `true` if any sample is NaN or infinite.
+    pub fn has_non_finite(&self) -> bool {
+        self.samples.iter().any(|s| !s.is_finite())
+    }
 }
 
 impl From<Xyza> for RgbaSpectrum {
@@ -124,6 +154,10 @@ impl From<Xyza> for RgbaSpectrum {
     }
 }
 
+/// Quantizes each sample from [0, 1] to [0, 255], clamping samples outside of
+/// that range. The samples are quantized as they are: no transfer function is
+/// applied to the color, and the color isn't multiplied or divided by alpha, so
+/// the caller is responsible for encoding it the way the image expects.
 impl From<RgbaSpectrum> for image::Rgb<u8> {
     fn from(rgb: RgbaSpectrum) -> Self {
         image::Rgb([
@@ -134,6 +168,8 @@ impl From<RgbaSpectrum> for image::Rgb<u8> {
     }
 }
 
+/// Quantizes each sample from [0, 1] to [0, 255], like the conversion to
+/// `image::Rgb<u8>`.
 impl From<RgbaSpectrum> for image::Rgba<u8> {
     fn from(rgb: RgbaSpectrum) -> Self {
         image::Rgba([
@@ -145,6 +181,19 @@ impl From<RgbaSpectrum> for image::Rgba<u8> {
     }
 }
 
+/// Quantizes each sample from [0, 1] to [0, 65535], like the conversion to
+/// `image::Rgb<u8>`.
+impl From<RgbaSpectrum> for image::Rgba<u16> {
+    fn from(rgb: RgbaSpectrum) -> Self {
+        image::Rgba([
+            component_f32_into_u16(rgb.r()),
+            component_f32_into_u16(rgb.g()),
+            component_f32_into_u16(rgb.b()),
+            component_f32_into_u16(rgb.a()),
+        ])
+    }
+}
+
 fn component_f32_into_u8(c: f32) -> u8 {
     if c < 0.0 {
         0
@@ -155,6 +204,16 @@ fn component_f32_into_u8(c: f32) -> u8 {
     }
 }
 
+fn component_f32_into_u16(c: f32) -> u16 {
+    if c < 0.0 {
+        0
+    } else if c >= 1.0 {
+        65535
+    } else {
+        (c * 65536.0) as u16
+    }
+}
+
 // Spectrum addition
 
 impl Add<RgbaSpectrum> for RgbaSpectrum {
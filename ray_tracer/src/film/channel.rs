@@ -0,0 +1,466 @@
+use crate::color::RgbaSpectrum;
+use cgmath::Vector3;
+use std::ops::Range;
+
+/// The name of the standard channel that holds the depth of the visible
+/// surface in each pixel, as recorded by `VisibleSurface::depth`.
+pub const DEPTH: &str = "depth";
+
+/// The name of the standard channel that holds the world space normal of the
+/// visible surface in each pixel, as recorded by `VisibleSurface::normal`.
+pub const NORMAL: &str = "normal";
+
+/// The name of the standard channel that holds the albedo of the visible
+/// surface in each pixel, as recorded by `VisibleSurface::albedo`.
+pub const ALBEDO: &str = "albedo";
+
+/// The name of the standard channel that holds the index of the visible
+/// triangle in its mesh in each pixel, as recorded by
+/// `VisibleSurface::triangle_index`.
+pub const TRIANGLE_INDEX: &str = "triangle_index";
+
+/// The name of the standard channel that holds the index of the visible mesh
+/// in each pixel, as recorded by `VisibleSurface::mesh_index`.
+pub const MESH_INDEX: &str = "mesh_index";
+
+/// The value that ID channels hold in pixels in which no sample had a value
+/// for them.
+pub const NO_INDEX: u32 = u32::MAX;
+
+/// How the values that samples have for a channel are combined into the value
+/// of a pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reconstruction {
+    /// The values are reconstructed with the film's filter, like radiance is,
+    /// from every sample near the pixel that has a value. This suits values
+    /// that vary smoothly, like albedo or shadowing.
+    Filtered,
+
+    /// The pixel takes the value of the sample in it that's nearest to its
+    /// center.
+    Nearest,
+
+    /// The pixel takes the value of the sample in it whose camera ray hit the
+    /// nearest surface, so that its value belongs to a real surface rather
+    /// than being a blend of several. This suits values like depth or normals.
+    MinDepth,
+
+    /// Like `MinDepth`, for integer identifiers like the index of a triangle,
+    /// which are kept exactly.
+    Id,
+}
+
+/// An auxiliary image that the film records alongside the radiance, which is
+/// sometimes called an arbitrary output variable, or AOV.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    /// The name of the channel. Samples' values are written to the channel
+    /// with the same name.
+    pub name: String,
+
+    /// The number of components in the channel's values. ID channels have one.
+    pub components: usize,
+
+    pub reconstruction: Reconstruction,
+
+    /// The value of each component in pixels in which no sample had a value
+    /// for the channel. This is ignored by ID channels, which hold `NO_INDEX`
+    /// instead.
+    pub missing_value: f32,
+}
+
+impl Channel {
+    /// Panics if `components` is zero, or if it isn't one for an ID channel.
+    pub fn new(name: &str, components: usize, reconstruction: Reconstruction) -> Self {
+        assert!(components > 0);
+        assert!(reconstruction != Reconstruction::Id || components == 1);
+        Self {
+            name: name.to_string(),
+            components,
+            reconstruction,
+            missing_value: 0.0,
+        }
+    }
+
+    /// Return the channel, with `missing_value` in the pixels in which no
+    /// sample had a value for it.
+    pub fn with_missing_value(mut self, missing_value: f32) -> Self {
+        self.missing_value = missing_value;
+        self
+    }
+
+    /// The standard channel of depths. Pixels in which no sample hit a surface
+    /// are infinitely deep.
+    pub fn depth() -> Self {
+        Self::new(DEPTH, 1, Reconstruction::MinDepth).with_missing_value(f32::INFINITY)
+    }
+
+    /// The standard channel of world space normals. Pixels in which no sample
+    /// hit a surface are zero.
+    pub fn normal() -> Self {
+        Self::new(NORMAL, 3, Reconstruction::MinDepth)
+    }
+
+    /// The standard channel of linear RGB albedos, filtered over the samples
+    /// that hit a surface. Pixels in which no sample hit a surface are zero.
+    pub fn albedo() -> Self {
+        Self::new(ALBEDO, 3, Reconstruction::Filtered)
+    }
+
+    /// The standard channel of triangle indices.
+    pub fn triangle_index() -> Self {
+        Self::new(TRIANGLE_INDEX, 1, Reconstruction::Id)
+    }
+
+    /// The standard channel of mesh indices.
+    pub fn mesh_index() -> Self {
+        Self::new(MESH_INDEX, 1, Reconstruction::Id)
+    }
+
+    /// Return the number of values that pixels store for the channel: the
+    /// components of the value, followed by the filter weight sum of a
+    /// filtered channel, or the distance or depth that the sample whose value
+    /// was kept was chosen by. ID channels store their IDs separately, so they
+    /// store only the depth.
+    fn values_len(&self) -> usize {
+        match self.reconstruction {
+            Reconstruction::Id => 1,
+            _ => self.components + 1,
+        }
+    }
+
+    /// Return the number of IDs that pixels store for the channel, which is
+    /// one for an ID channel and zero otherwise.
+    fn ids_len(&self) -> usize {
+        match self.reconstruction {
+            Reconstruction::Id => 1,
+            _ => 0,
+        }
+    }
+
+    /// Return the values of a pixel that no sample has contributed to.
+    fn initial_values(&self) -> impl Iterator<Item = f32> {
+        let key = match self.reconstruction {
+            Reconstruction::Filtered => 0.0,
+            // The distance or depth is NaN until a sample is kept.
+            _ => f32::NAN,
+        };
+        std::iter::repeat_n(0.0, self.values_len() - 1).chain(std::iter::once(key))
+    }
+
+    /// Add the value of a sample near the pixel to the state of a filtered
+    /// channel.
+    fn add_filtered(&self, state: PixelStatesMut, value: &AovValue, weight: f32) {
+        let (components, weight_sum) = state.values.split_at_mut(self.components);
+        value.for_each_component(components, |sum, c| *sum += weight * c);
+        weight_sum[0] += weight;
+    }
+
+    /// Keep the value of a sample in the pixel in the state of an unfiltered
+    /// channel, if its `key` is less than that of the value that's kept. ID
+    /// channels only keep `AovValue::Id`s.
+    fn add_unfiltered(&self, state: PixelStatesMut, value: &AovValue, key: f32) {
+        let (components, kept_key) = state.values.split_at_mut(self.values_len() - 1);
+        if !kept_key[0].is_nan() && key >= kept_key[0] {
+            return;
+        }
+        match (self.reconstruction, value) {
+            (Reconstruction::Id, AovValue::Id(id)) => state.ids[0] = *id,
+            (Reconstruction::Id, _) => return,
+            _ => value.for_each_component(components, |kept, c| *kept = c),
+        }
+        kept_key[0] = key;
+    }
+
+    /// Combine the state of another pixel into the state of the pixel.
+    fn merge(&self, state: PixelStatesMut, other: PixelStates) {
+        let key = self.values_len() - 1;
+        match self.reconstruction {
+            Reconstruction::Filtered => {
+                for (s, o) in state.values.iter_mut().zip(other.values) {
+                    *s += o;
+                }
+            }
+            _ => {
+                let (state_key, other_key) = (state.values[key], other.values[key]);
+                if !other_key.is_nan() && (state_key.is_nan() || other_key < state_key) {
+                    state.values.copy_from_slice(other.values);
+                    state.ids.copy_from_slice(other.ids);
+                }
+            }
+        }
+    }
+
+    /// Write the reconstructed value of a pixel with the given state to `out`.
+    /// Panics for an ID channel, whose IDs are resolved by `resolve_id`.
+    fn resolve(&self, state: PixelStates, out: &mut [f32]) {
+        let (components, key) = state.values.split_at(self.components);
+        match self.reconstruction {
+            Reconstruction::Filtered if key[0] != 0.0 => {
+                for (out, c) in out.iter_mut().zip(components) {
+                    *out = c / key[0];
+                }
+            }
+            Reconstruction::Id => panic!("ID channels are resolved with resolve_id"),
+            Reconstruction::Nearest | Reconstruction::MinDepth if !key[0].is_nan() => {
+                out.copy_from_slice(components)
+            }
+            _ => out.iter_mut().for_each(|out| *out = self.missing_value),
+        }
+    }
+
+    /// Return the reconstructed ID of a pixel with the given state of an ID
+    /// channel, or `NO_INDEX` if no sample had a value for the channel.
+    fn resolve_id(&self, state: PixelStates) -> u32 {
+        if state.values[0].is_nan() {
+            NO_INDEX
+        } else {
+            state.ids[0]
+        }
+    }
+}
+
+/// A value that a sample has for a channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AovValue {
+    Scalar(f32),
+    Vector(Vector3<f32>),
+
+    /// A color, whose red, green and blue components are recorded.
+    Color(RgbaSpectrum),
+
+    /// An integer identifier, for ID channels.
+    Id(u32),
+}
+
+impl AovValue {
+    /// Call `f` with each component of the value and the corresponding entry
+    /// of `out`. Components beyond the end of `out` are ignored. IDs are
+    /// converted to floats, which loses precision above 2^24, so ID channels
+    /// keep them as integers instead.
+    fn for_each_component(&self, out: &mut [f32], mut f: impl FnMut(&mut f32, f32)) {
+        let components = match *self {
+            AovValue::Scalar(s) => [s, 0.0, 0.0],
+            AovValue::Vector(v) => [v.x, v.y, v.z],
+            AovValue::Color(c) => [c.r(), c.g(), c.b()],
+            AovValue::Id(id) => [id as f32, 0.0, 0.0],
+        };
+        for (out, c) in out.iter_mut().zip(&components) {
+            f(out, *c);
+        }
+    }
+}
+
+/// A value that a sample has for the channel with the given name, beyond the
+/// standard channels that are recorded from its `VisibleSurface`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aov {
+    pub name: &'static str,
+    pub value: AovValue,
+}
+
+/// The states of every channel in a run of pixels. The IDs that ID channels
+/// keep are stored apart from the other values, as integers, so that they're
+/// kept exactly.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChannelStates {
+    values: Vec<f32>,
+    ids: Vec<u32>,
+}
+
+/// The states of some or all of the channels of one pixel.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PixelStates<'a> {
+    values: &'a [f32],
+    ids: &'a [u32],
+}
+
+/// The mutable states of some or all of the channels of one pixel.
+#[derive(Debug)]
+pub(super) struct PixelStatesMut<'a> {
+    values: &'a mut [f32],
+    ids: &'a mut [u32],
+}
+
+/// The channels that a film records, and where each one's state is stored in
+/// the run of values and IDs that each pixel stores for all of them.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChannelLayout {
+    channels: Vec<Channel>,
+
+    /// The ranges of each channel's values and IDs in those of a pixel.
+    ranges: Vec<(Range<usize>, Range<usize>)>,
+
+    /// The number of values and IDs that each pixel stores.
+    strides: (usize, usize),
+}
+
+impl ChannelLayout {
+    /// Add a channel to the layout, replacing any channel with the same name.
+    pub(super) fn add(&mut self, channel: Channel) {
+        let mut channels = std::mem::take(&mut self.channels);
+        channels.retain(|c| c.name != channel.name);
+        channels.push(channel);
+        self.ranges = channels
+            .iter()
+            .scan((0, 0), |(values_offset, ids_offset), c| {
+                let values = *values_offset..*values_offset + c.values_len();
+                let ids = *ids_offset..*ids_offset + c.ids_len();
+                *values_offset = values.end;
+                *ids_offset = ids.end;
+                Some((values, ids))
+            })
+            .collect();
+        self.strides = (
+            channels.iter().map(Channel::values_len).sum(),
+            channels.iter().map(Channel::ids_len).sum(),
+        );
+        self.channels = channels;
+    }
+
+    pub(super) fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    /// Return the channel with the given name and its index in the layout, if
+    /// there is one.
+    pub(super) fn find(&self, name: &str) -> Option<(&Channel, usize)> {
+        self.channels
+            .iter()
+            .enumerate()
+            .find(|(_, c)| c.name == name)
+            .map(|(index, c)| (c, index))
+    }
+
+    /// Return the states of `pixel_count` pixels that no sample has
+    /// contributed to.
+    pub(super) fn initial_states(&self, pixel_count: usize) -> ChannelStates {
+        let values: Vec<f32> = self
+            .channels
+            .iter()
+            .flat_map(|c| c.initial_values())
+            .collect();
+        ChannelStates {
+            values: values.repeat(pixel_count),
+            ids: vec![NO_INDEX; self.strides.1 * pixel_count],
+        }
+    }
+
+    /// Return the states of the pixel at the index in `states`.
+    pub(super) fn pixel_states<'a>(
+        &self,
+        states: &'a ChannelStates,
+        index: usize,
+    ) -> PixelStates<'a> {
+        let (values_stride, ids_stride) = self.strides;
+        PixelStates {
+            values: &states.values[index * values_stride..(index + 1) * values_stride],
+            ids: &states.ids[index * ids_stride..(index + 1) * ids_stride],
+        }
+    }
+
+    pub(super) fn pixel_states_mut<'a>(
+        &self,
+        states: &'a mut ChannelStates,
+        index: usize,
+    ) -> PixelStatesMut<'a> {
+        let (values_stride, ids_stride) = self.strides;
+        PixelStatesMut {
+            values: &mut states.values[index * values_stride..(index + 1) * values_stride],
+            ids: &mut states.ids[index * ids_stride..(index + 1) * ids_stride],
+        }
+    }
+
+    /// Return the states of the channel at the index among the states of a
+    /// pixel.
+    fn channel_states<'a>(&self, pixel_states: PixelStates<'a>, index: usize) -> PixelStates<'a> {
+        let (values, ids) = self.ranges[index].clone();
+        PixelStates {
+            values: &pixel_states.values[values],
+            ids: &pixel_states.ids[ids],
+        }
+    }
+
+    fn channel_states_mut<'a>(
+        &self,
+        pixel_states: &'a mut PixelStatesMut,
+        index: usize,
+    ) -> PixelStatesMut<'a> {
+        let (values, ids) = self.ranges[index].clone();
+        PixelStatesMut {
+            values: &mut pixel_states.values[values],
+            ids: &mut pixel_states.ids[ids],
+        }
+    }
+
+    /// Add a sample's values to the filtered channels of a pixel near it.
+    ///
+    /// * pixel_states - The states of the pixel.
+    /// * value - Returns the sample's value for a channel, if it has one.
+    /// * weight - The filter weight of the sample at the pixel.
+    pub(super) fn add_filtered(
+        &self,
+        mut pixel_states: PixelStatesMut,
+        value: impl Fn(&str) -> Option<AovValue>,
+        weight: f32,
+    ) {
+        for (index, channel) in self.channels.iter().enumerate() {
+            if channel.reconstruction == Reconstruction::Filtered {
+                if let Some(value) = value(&channel.name) {
+                    let state = self.channel_states_mut(&mut pixel_states, index);
+                    channel.add_filtered(state, &value, weight);
+                }
+            }
+        }
+    }
+
+    /// Add a sample's values to the unfiltered channels of the pixel that it
+    /// was taken in.
+    ///
+    /// * pixel_states - The states of the pixel.
+    /// * value - Returns the sample's value for a channel, if it has one.
+    /// * center_distance_squared - The squared distance from the sample to
+    ///   the center of the pixel.
+    /// * depth - The depth of the surface that the sample's camera ray hit
+    ///   first, or infinity if it didn't hit one.
+    pub(super) fn add_unfiltered(
+        &self,
+        mut pixel_states: PixelStatesMut,
+        value: impl Fn(&str) -> Option<AovValue>,
+        center_distance_squared: f32,
+        depth: f32,
+    ) {
+        for (index, channel) in self.channels.iter().enumerate() {
+            let key = match channel.reconstruction {
+                Reconstruction::Filtered => continue,
+                Reconstruction::Nearest => center_distance_squared,
+                Reconstruction::MinDepth | Reconstruction::Id => depth,
+            };
+            if let Some(value) = value(&channel.name) {
+                let state = self.channel_states_mut(&mut pixel_states, index);
+                channel.add_unfiltered(state, &value, key);
+            }
+        }
+    }
+
+    /// Combine the states of another pixel into the states of the pixel.
+    pub(super) fn merge(&self, mut pixel_states: PixelStatesMut, other: PixelStates) {
+        for (index, channel) in self.channels.iter().enumerate() {
+            channel.merge(
+                self.channel_states_mut(&mut pixel_states, index),
+                self.channel_states(other, index),
+            );
+        }
+    }
+
+    /// Write the reconstructed value in a pixel of the channel at the index to
+    /// `out`. Panics for an ID channel.
+    pub(super) fn resolve(&self, index: usize, pixel_states: PixelStates, out: &mut [f32]) {
+        self.channels[index].resolve(self.channel_states(pixel_states, index), out);
+    }
+
+    /// Return the reconstructed ID in a pixel of the ID channel at the index.
+    pub(super) fn resolve_id(&self, index: usize, pixel_states: PixelStates) -> u32 {
+        self.channels[index].resolve_id(self.channel_states(pixel_states, index))
+    }
+}
@@ -0,0 +1,209 @@
+use super::{Film, ALBEDO, DEPTH, NORMAL};
+use crate::color::{RgbaSpectrum, Xyza};
+use cgmath::{InnerSpace, Vector3};
+
+/// Removes noise from a rendered film with a joint bilateral filter, which
+/// averages each pixel with its neighbors, weighted by how similar their
+/// surfaces are.
+///
+/// The film's albedo, normal and depth channels guide the filter, so that it
+/// blurs the noise on each surface without blurring edges between surfaces,
+/// or the texture of their albedo. Any of them that the film doesn't record
+/// is left out. The radiance is divided by the albedo before it's filtered and
+/// multiplied by it afterwards, so that only the lighting is blurred. Pixels
+/// whose samples agree closely, which the film's pixel statistics show, are
+/// also blurred less, so that the filter mostly leaves converged regions, like
+/// the hard edges of shadows, alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BilateralDenoiser {
+    /// The standard deviation, in pixels, of the Gaussian that weights
+    /// neighbors by their distance.
+    pub spatial_sigma: f32,
+
+    /// The standard deviation of the Gaussian that weights neighbors by the
+    /// difference between their albedo and the pixel's.
+    pub albedo_sigma: f32,
+
+    /// The scale of the exponential that weights neighbors by one minus the
+    /// cosine of the angle between their normal and the pixel's.
+    pub normal_sigma: f32,
+
+    /// The standard deviation of the Gaussian that weights neighbors by the
+    /// difference between their depth and the pixel's, relative to the
+    /// pixel's depth.
+    pub depth_sigma: f32,
+
+    /// The number of standard errors of the pixels' mean luminance by which a
+    /// neighbor's luminance can differ from the pixel's before it's given
+    /// little weight.
+    pub variance_scale: f32,
+}
+
+impl BilateralDenoiser {
+    /// Create a denoiser that blurs noise more the greater `strength` is. A
+    /// strength of 1 suits images with a few samples per pixel, and a strength
+    /// of 0 leaves images unchanged.
+    pub fn new(strength: f32) -> Self {
+        Self {
+            spatial_sigma: 2.0 * strength,
+            albedo_sigma: 0.1,
+            normal_sigma: 0.1,
+            depth_sigma: 0.05,
+            variance_scale: 3.0 * strength,
+        }
+    }
+}
+
+/// What the denoiser knows about a pixel.
+#[derive(Clone, Copy)]
+struct Guide {
+    /// The pixel's color divided by its alpha and `modulation`.
+    irradiance: RgbaSpectrum,
+    alpha: f32,
+    albedo: Option<Vector3<f32>>,
+
+    /// The albedo that the pixel's color is divided by before it's filtered,
+    /// and multiplied by afterwards.
+    modulation: Vector3<f32>,
+    normal: Option<Vector3<f32>>,
+    depth: Option<f32>,
+    luminance: f32,
+
+    /// The variance of the mean luminance of the pixel's samples, if there
+    /// were enough samples to estimate it.
+    variance: Option<f32>,
+}
+
+impl Film {
+    /// Remove noise from the radiance recorded by the film, leaving its
+    /// alpha, auxiliary channels and pixel statistics unchanged. This should
+    /// be called after every tile has been merged into the film.
+    pub fn denoise(&mut self, denoiser: &BilateralDenoiser) {
+        if denoiser.spatial_sigma <= 0.0 {
+            return;
+        }
+        let guides = self.denoising_guides();
+        let (width, height) = (self.resolution.x as i32, self.resolution.y as i32);
+        let radius = (2.0 * denoiser.spatial_sigma).ceil() as i32;
+
+        let mut filtered = Vec::with_capacity(guides.len());
+        for (index, guide) in guides.iter().enumerate() {
+            let guide = match guide {
+                Some(guide) => guide,
+                None => {
+                    filtered.push(None);
+                    continue;
+                }
+            };
+            let (x, y) = (index as i32 % width, index as i32 / width);
+            let mut irradiance_sum = RgbaSpectrum::constant(0.0);
+            let mut weight_sum = 0.0;
+            for ny in (y - radius).max(0)..=(y + radius).min(height - 1) {
+                for nx in (x - radius).max(0)..=(x + radius).min(width - 1) {
+                    if let Some(neighbor) = &guides[(ny * width + nx) as usize] {
+                        let distance_squared = ((nx - x).pow(2) + (ny - y).pow(2)) as f32;
+                        let weight = denoiser.weight(guide, neighbor, distance_squared);
+                        irradiance_sum += weight * neighbor.irradiance;
+                        weight_sum += weight;
+                    }
+                }
+            }
+            // The pixel's own weight is one, so the sum is never zero.
+            let irradiance = (1.0 / weight_sum) * irradiance_sum;
+            let modulation = guide.modulation;
+            filtered.push(Some(RgbaSpectrum::from_rgba(
+                guide.alpha * modulation.x * irradiance.r(),
+                guide.alpha * modulation.y * irradiance.g(),
+                guide.alpha * modulation.z * irradiance.b(),
+                guide.alpha,
+            )));
+        }
+
+        for (pixel, color) in self.pixels.iter_mut().zip(filtered) {
+            if let Some(color) = color {
+                pixel.xyz = Xyza::from(pixel.filter_weight_sum * color);
+            }
+        }
+    }
+
+    /// Return what the denoiser knows about each pixel, or `None` for pixels
+    /// that don't have any radiance to denoise.
+    fn denoising_guides(&self) -> Vec<Option<Guide>> {
+        let albedo = self.write_vector_channel(ALBEDO);
+        let normal = self.write_vector_channel(NORMAL);
+        let depth = self.write_scalar_channel(DEPTH);
+        (0..self.pixels.len())
+            .map(|index| {
+                let (x, y) = (
+                    (index % self.resolution.x) as u32,
+                    (index / self.resolution.x) as u32,
+                );
+                let pixel = &self.pixels[index];
+                let color = self.pixel_color(x, y);
+                let depth = depth.as_ref().map(|depth| depth.get_pixel(x, y).0[0]);
+                if pixel.filter_weight_sum <= 0.0
+                    || color.a() <= 0.0
+                    || depth.is_some_and(|depth| !depth.is_finite())
+                {
+                    return None;
+                }
+                let albedo = albedo
+                    .as_ref()
+                    .map(|albedo| Vector3::from(albedo.get_pixel(x, y).0));
+                // Dark albedos are clamped so that dividing by them doesn't
+                // amplify noise.
+                let modulation = albedo.map_or(Vector3::new(1.0, 1.0, 1.0), |albedo| {
+                    albedo.map(|c| c.max(0.01))
+                });
+                let statistics = &pixel.statistics;
+                Some(Guide {
+                    irradiance: RgbaSpectrum::from_rgb(
+                        color.r() / (color.a() * modulation.x),
+                        color.g() / (color.a() * modulation.y),
+                        color.b() / (color.a() * modulation.z),
+                    ),
+                    alpha: color.a(),
+                    albedo,
+                    modulation,
+                    normal: normal
+                        .as_ref()
+                        .map(|normal| Vector3::from(normal.get_pixel(x, y).0)),
+                    depth,
+                    luminance: statistics.mean(),
+                    variance: statistics
+                        .variance()
+                        .map(|variance| variance / statistics.sample_count as f32),
+                })
+            })
+            .collect()
+    }
+}
+
+impl BilateralDenoiser {
+    /// Return the weight of a neighbor of a pixel, given the squared distance
+    /// between them in pixels.
+    fn weight(&self, pixel: &Guide, neighbor: &Guide, distance_squared: f32) -> f32 {
+        let mut exponent = -distance_squared / (2.0 * self.spatial_sigma.powi(2));
+        if let (Some(a), Some(b)) = (pixel.albedo, neighbor.albedo) {
+            exponent -= (a - b).magnitude2() / (2.0 * self.albedo_sigma.powi(2));
+        }
+        if let (Some(a), Some(b)) = (pixel.normal, neighbor.normal) {
+            exponent -= (1.0 - a.dot(b)).max(0.0) / self.normal_sigma;
+        }
+        if let (Some(a), Some(b)) = (pixel.depth, neighbor.depth) {
+            exponent -= ((a - b) / (self.depth_sigma * a.max(1e-3))).powi(2) / 2.0;
+        }
+        if let (Some(a), Some(b)) = (pixel.variance, neighbor.variance) {
+            let variance = self.variance_scale.powi(2) * (a + b);
+            let difference = (pixel.luminance - neighbor.luminance).powi(2);
+            exponent -= if variance > 0.0 {
+                difference / (2.0 * variance)
+            } else if difference > 0.0 {
+                f32::INFINITY
+            } else {
+                0.0
+            };
+        }
+        exponent.exp()
+    }
+}
@@ -0,0 +1,27 @@
+/// The transfer function that's applied to linear values before they're
+/// quantized to the integers of an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorEncoding {
+    /// Encode values with the sRGB transfer function, which spends more of an
+    /// image's integers on dark values, where the eye is more sensitive to
+    /// differences. This is what displays and image viewers expect.
+    #[default]
+    Srgb,
+
+    /// Quantize linear values as they are. Dark areas of an image look too
+    /// dark, and show banding, unless the viewer knows the image is linear.
+    Linear,
+}
+
+/// How the alpha of a partially transparent pixel relates to its color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AlphaMode {
+    /// The color is the color of whatever covers the pixel, independent of
+    /// alpha, which is what PNG and most image formats expect.
+    #[default]
+    Straight,
+
+    /// The color has been multiplied by alpha, so it's what the pixel adds to
+    /// a background that it's composited over.
+    Premultiplied,
+}
@@ -1,14 +1,32 @@
+mod channel;
+mod denoise;
+mod encoding;
+mod statistics;
 mod tile;
+mod tone_map;
+mod visible_surface;
 
-use self::tile::FilmTilePixel;
+use self::{
+    channel::{ChannelLayout, ChannelStates, PixelStates},
+    tile::FilmTilePixel,
+};
 use crate::{
     color::{RgbaSpectrum, Xyza},
     geometry::bounds::Bounds2,
 };
-use cgmath::{point2, Point2, Vector2};
+use cgmath::{point2, InnerSpace, Matrix4, Point2, Transform, Vector2, Vector3, Zero};
 use image::ImageBuffer;
 
+pub use channel::{
+    Aov, AovValue, Channel, Reconstruction, ALBEDO, DEPTH, MESH_INDEX, NORMAL, NO_INDEX,
+    TRIANGLE_INDEX,
+};
+pub use denoise::BilateralDenoiser;
+pub use encoding::{AlphaMode, ColorEncoding};
+pub use statistics::PixelStatistics;
 pub use tile::FilmTile;
+pub use tone_map::ToneMap;
+pub use visible_surface::VisibleSurface;
 
 /// Models the sensing device in a simulated camera. Acts as a 2D plane of
 /// pixels onto which a final image is rendered.
@@ -28,6 +46,22 @@ pub struct Film {
     pub pixel_bounds: Bounds2<i32>,
 
     pixels: Vec<FilmPixel>,
+
+    /// The film's auxiliary channels, and the state of each of them in each
+    /// pixel, laid out one pixel after another in the same order as `pixels`.
+    channels: ChannelLayout,
+    channel_states: ChannelStates,
+
+    /// Samples whose luminance is greater than this are scaled down to this
+    /// luminance before they're added to the film.
+    max_sample_luminance: f32,
+
+    /// The number of stops by which the image is brightened, or darkened if
+    /// it's negative, before it's tone mapped.
+    exposure: f32,
+    tone_map: ToneMap,
+    color_encoding: ColorEncoding,
+    alpha_mode: AlphaMode,
 }
 
 impl Film {
@@ -42,9 +76,71 @@ impl Film {
             resolution,
             pixel_bounds,
             pixels,
+            channels: ChannelLayout::default(),
+            channel_states: ChannelStates::default(),
+            max_sample_luminance: f32::INFINITY,
+            exposure: 0.0,
+            tone_map: ToneMap::default(),
+            color_encoding: ColorEncoding::default(),
+            alpha_mode: AlphaMode::default(),
         }
     }
 
+    /// Return the film, with the image written by `write_image` brightened by
+    /// `exposure` stops, or darkened if `exposure` is negative.
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Return the film, with the image written by `write_image` mapped to the
+    /// range of a display with `tone_map`, rather than clamped.
+    pub fn with_tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.tone_map = tone_map;
+        self
+    }
+
+    /// Return the film, with the colors of the image written by `write_image`
+    /// encoded with `color_encoding`.
+    pub fn with_color_encoding(mut self, color_encoding: ColorEncoding) -> Self {
+        self.color_encoding = color_encoding;
+        self
+    }
+
+    /// Return the film, with the colors of the image written by `write_image`
+    /// related to its alpha by `alpha_mode`.
+    pub fn with_alpha_mode(mut self, alpha_mode: AlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    /// Return the film, recording `channel` alongside the radiance. This
+    /// replaces any channel with the same name. Channels should be added
+    /// before any tiles are created.
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channels.add(channel);
+        self.channel_states = self.channels.initial_states(self.pixels.len());
+        self
+    }
+
+    /// Return the auxiliary channels that the film records.
+    pub fn channels(&self) -> &[Channel] {
+        self.channels.channels()
+    }
+
+    /// Return the film, with samples whose luminance is greater than
+    /// `max_sample_luminance` scaled down to that luminance before they're
+    /// added to it.
+    ///
+    /// Rare samples with very high radiance, such as light paths that happen
+    /// to hit a light after a specular bounce, show up as bright speckles,
+    /// called fireflies, that take many samples to average out. Clamping
+    /// samples removes fireflies at the cost of darkening the image slightly.
+    pub fn with_max_sample_luminance(mut self, max_sample_luminance: f32) -> Self {
+        self.max_sample_luminance = max_sample_luminance;
+        self
+    }
+
     /// Return a bounding box around the film's pixels in raster space that a
     /// `Sampler` will be responsible for generating samples for.
     ///
@@ -89,39 +185,267 @@ impl Film {
         filter_half_height: f32,
     ) -> Option<FilmTile> {
         self.pixel_bounds_for_sample_bounds(sample_bounds, filter_half_width, filter_half_height)
-            .map(FilmTile::new)
+            .map(|pixel_bounds| {
+                FilmTile::new(pixel_bounds, self.max_sample_luminance).with_channels(&self.channels)
+            })
     }
 
     /// Merge the tile into the film.
     pub fn merge_tile(&mut self, tile: &FilmTile) {
         for pixel_min_corner in tile.pixel_bounds.range() {
             if let Some(pixel) = tile.pixel_at(pixel_min_corner) {
-                self.merge_pixel(pixel, &pixel_min_corner);
+                let channel_states = tile.channel_states_at(pixel_min_corner);
+                self.merge_pixel(pixel, channel_states, &pixel_min_corner);
             }
         }
     }
 
-    /// Write the contents of the film to an image buffer.
+    /// Write the contents of the film to an image buffer, after applying the
+    /// film's exposure and tone map.
+    ///
+    /// By default, colors are encoded with the sRGB transfer function and
+    /// have straight alpha, as PNG expects. `with_color_encoding` and
+    /// `with_alpha_mode` change that.
     pub fn write_image(&self) -> image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>> {
         ImageBuffer::from_fn(
             self.resolution.x as u32,
             self.resolution.y as u32,
             |x, y| {
-                let index = self.pixel_index(&point2(x as i32, y as i32));
-                let pixel = self.pixels[index];
+                let output: image::Rgba<u8> = self.display_color(self.pixel_color(x, y)).into();
+                output
+            },
+        )
+    }
 
-                let color = if pixel.filter_weight_sum > 0.0 {
-                    (1.0 / pixel.filter_weight_sum) * RgbaSpectrum::from(pixel.xyz)
-                } else {
-                    RgbaSpectrum::transparent()
-                };
+    /// Write the contents of the film to an image buffer with 16 bits per
+    /// channel, which has less banding than `write_image` and keeps more
+    /// precision for measurements taken from the image. Colors are encoded the
+    /// same way as `write_image` encodes them.
+    pub fn write_image_16(&self) -> image::ImageBuffer<image::Rgba<u16>, std::vec::Vec<u16>> {
+        ImageBuffer::from_fn(
+            self.resolution.x as u32,
+            self.resolution.y as u32,
+            |x, y| self.display_color(self.pixel_color(x, y)).into(),
+        )
+    }
 
-                let output: image::Rgba<u8> = color.into();
-                output
+    /// Write the channel with the given name to an image buffer, if the film
+    /// records a channel with that name and one component that isn't an ID.
+    pub fn write_scalar_channel(
+        &self,
+        name: &str,
+    ) -> Option<image::ImageBuffer<image::Luma<f32>, std::vec::Vec<f32>>> {
+        self.write_channel(name, 1, |channel| {
+            channel.reconstruction != Reconstruction::Id
+        })
+    }
+
+    /// Write the channel with the given name to an image buffer, if the film
+    /// records a channel with that name and three components.
+    pub fn write_vector_channel(
+        &self,
+        name: &str,
+    ) -> Option<image::ImageBuffer<image::Rgb<f32>, std::vec::Vec<f32>>> {
+        self.write_channel(name, 3, |_| true)
+    }
+
+    /// Write the channel with the given name to an image buffer, if the film
+    /// records an ID channel with that name. Pixels in which no sample had a
+    /// value for the channel are `NO_INDEX`.
+    pub fn write_id_channel(
+        &self,
+        name: &str,
+    ) -> Option<image::ImageBuffer<image::Luma<u32>, std::vec::Vec<u32>>> {
+        let (channel, index) = self.channels.find(name)?;
+        if channel.reconstruction != Reconstruction::Id {
+            return None;
+        }
+        let ids = (0..self.pixels.len())
+            .map(|pixel| {
+                let pixel_states = self.channels.pixel_states(&self.channel_states, pixel);
+                self.channels.resolve_id(index, pixel_states)
+            })
+            .collect();
+        ImageBuffer::from_raw(self.resolution.x as u32, self.resolution.y as u32, ids)
+    }
+
+    /// Write the depth of the nearest surface that was hit by a sample in each
+    /// pixel to an image buffer, if the film records the standard depth
+    /// channel. Pixels in which no sample hit a surface are infinitely deep.
+    pub fn write_depth(&self) -> Option<image::ImageBuffer<image::Luma<f32>, std::vec::Vec<f32>>> {
+        self.write_scalar_channel(DEPTH)
+    }
+
+    /// Write the normal of the nearest surface that was hit by a sample in each
+    /// pixel to an image buffer, if the film records the standard normal
+    /// channel. Pixels in which no sample hit a surface are zero.
+    ///
+    /// * world_to_space - Transforms the normals from world space to the space
+    ///   that they're written in.
+    pub fn write_normals(
+        &self,
+        world_to_space: &Matrix4<f32>,
+    ) -> Option<image::ImageBuffer<image::Rgb<f32>, std::vec::Vec<f32>>> {
+        let mut normals = self.write_vector_channel(NORMAL)?;
+        for pixel in normals.pixels_mut() {
+            let normal = Vector3::from(pixel.0);
+            if normal != Vector3::zero() {
+                pixel.0 = world_to_space.transform_vector(normal).normalize().into();
+            }
+        }
+        Some(normals)
+    }
+
+    /// Write the index of the triangle at the nearest surface that was hit by a
+    /// sample in each pixel to an image buffer, if the film records the
+    /// standard triangle index channel.
+    pub fn write_triangle_indices(
+        &self,
+    ) -> Option<image::ImageBuffer<image::Luma<u32>, std::vec::Vec<u32>>> {
+        self.write_id_channel(TRIANGLE_INDEX)
+    }
+
+    /// Write the index of the mesh at the nearest surface that was hit by a
+    /// sample in each pixel to an image buffer, if the film records the
+    /// standard mesh index channel.
+    pub fn write_mesh_indices(
+        &self,
+    ) -> Option<image::ImageBuffer<image::Luma<u32>, std::vec::Vec<u32>>> {
+        self.write_id_channel(MESH_INDEX)
+    }
+
+    /// Write the reconstructed values of the channel with the given name to an
+    /// image buffer, if the film records a channel with that name and the
+    /// number of components of `P` that `is_kind` accepts.
+    fn write_channel<P: image::Pixel<Subpixel = f32>>(
+        &self,
+        name: &str,
+        components: usize,
+        is_kind: impl Fn(&Channel) -> bool,
+    ) -> Option<image::ImageBuffer<P, std::vec::Vec<f32>>> {
+        let (channel, index) = self.channels.find(name)?;
+        if channel.components != components || !is_kind(channel) {
+            return None;
+        }
+        let mut values = vec![0.0; self.pixels.len() * components];
+        for (pixel, out) in values.chunks_exact_mut(components).enumerate() {
+            let pixel_states = self.channels.pixel_states(&self.channel_states, pixel);
+            self.channels.resolve(index, pixel_states, out);
+        }
+        ImageBuffer::from_raw(self.resolution.x as u32, self.resolution.y as u32, values)
+    }
+
+    /// Write the contents of the film to an image buffer of linear RGB
+    /// radiance, without clamping or quantizing it, along with alpha.
+    ///
+    /// Transparent samples add black to a pixel, so the color of partially
+    /// transparent pixels is premultiplied by their alpha.
+    pub fn write_hdr(&self) -> image::ImageBuffer<image::Rgba<f32>, std::vec::Vec<f32>> {
+        ImageBuffer::from_fn(
+            self.resolution.x as u32,
+            self.resolution.y as u32,
+            |x, y| {
+                let color = self.pixel_color(x, y);
+                image::Rgba([color.r(), color.g(), color.b(), color.a()])
             },
         )
     }
 
+    /// Return the statistics about the samples taken in the pixel with the
+    /// given top-left corner, or `None` if the pixel isn't on the film.
+    pub fn statistics(&self, pixel_min_corner: Point2<i32>) -> Option<&PixelStatistics> {
+        let p = pixel_min_corner;
+        let bounds = &self.pixel_bounds;
+        if p.x < bounds.min.x || p.x >= bounds.max.x || p.y < bounds.min.y || p.y >= bounds.max.y {
+            return None;
+        }
+        Some(&self.pixels[self.pixel_index(&p)].statistics)
+    }
+
+    /// Return the average number of samples taken in each of the film's
+    /// pixels.
+    pub fn mean_samples_per_pixel(&self) -> f32 {
+        let sample_count: u64 = self
+            .pixels
+            .iter()
+            .map(|pixel| pixel.statistics.sample_count as u64)
+            .sum();
+        sample_count as f32 / self.pixels.len().max(1) as f32
+    }
+
+    /// Return the number of samples whose radiance had a NaN or infinite
+    /// component, which were dropped rather than added to the film.
+    pub fn non_finite_sample_count(&self) -> u64 {
+        self.pixels
+            .iter()
+            .map(|pixel| pixel.statistics.non_finite_sample_count as u64)
+            .sum()
+    }
+
+    /// Write an image that marks the pixels in which samples with NaN or
+    /// infinite radiance were taken. Marked pixels are opaque magenta, and
+    /// every other pixel is transparent.
+    pub fn write_non_finite_image(&self) -> image::ImageBuffer<image::Rgba<u8>, std::vec::Vec<u8>> {
+        ImageBuffer::from_fn(
+            self.resolution.x as u32,
+            self.resolution.y as u32,
+            |x, y| {
+                let index = self.pixel_index(&point2(x as i32, y as i32));
+                if self.pixels[index].statistics.non_finite_sample_count > 0 {
+                    image::Rgba([255, 0, 255, 255])
+                } else {
+                    image::Rgba([0, 0, 0, 0])
+                }
+            },
+        )
+    }
+
+    /// Return the color of the pixel at the given indices, which is the
+    /// filtered sum of the samples that contributed to it.
+    fn pixel_color(&self, x: u32, y: u32) -> RgbaSpectrum {
+        let index = self.pixel_index(&point2(x as i32, y as i32));
+        let pixel = self.pixels[index];
+
+        if pixel.filter_weight_sum > 0.0 {
+            (1.0 / pixel.filter_weight_sum) * RgbaSpectrum::from(pixel.xyz)
+        } else {
+            RgbaSpectrum::transparent()
+        }
+    }
+
+    /// Convert a pixel's linear color to the color written to the image, by
+    /// applying the film's exposure, tone map, color encoding and alpha mode.
+    ///
+    /// The color of partially transparent pixels is premultiplied by alpha, so
+    /// the color is divided by alpha before it's tone mapped. Otherwise the
+    /// tone map would treat the edges of the mesh as darker than they are. If
+    /// the alpha mode is premultiplied, the color is multiplied by alpha again
+    /// after it's encoded, since that's how images with integer colors are
+    /// composited.
+    fn display_color(&self, color: RgbaSpectrum) -> RgbaSpectrum {
+        let alpha = color.a();
+        if alpha <= 0.0 {
+            return RgbaSpectrum::transparent();
+        }
+        let scale = self.exposure.exp2() / alpha;
+        let map = |c: f32| self.tone_map.apply(scale * c);
+        let mut color =
+            RgbaSpectrum::from_rgba(map(color.r()), map(color.g()), map(color.b()), alpha);
+        if self.color_encoding == ColorEncoding::Srgb {
+            color = color.to_srgb();
+        }
+        if self.alpha_mode == AlphaMode::Premultiplied {
+            let alpha = alpha.min(1.0);
+            color = RgbaSpectrum::from_rgba(
+                alpha * color.r(),
+                alpha * color.g(),
+                alpha * color.b(),
+                color.a(),
+            );
+        }
+        color
+    }
+
     /// Return a bounding box around the pixels (in raster space) that samples
     /// taken from `sample_bounds` will contribute to.
     ///
@@ -158,10 +482,21 @@ impl Film {
     }
 
     /// Merge the pixel from the tile into the film.
-    fn merge_pixel(&mut self, pixel: &FilmTilePixel, pixel_min_corner: &Point2<i32>) {
+    fn merge_pixel(
+        &mut self,
+        pixel: &FilmTilePixel,
+        channel_states: PixelStates,
+        pixel_min_corner: &Point2<i32>,
+    ) {
         let index = self.pixel_index(pixel_min_corner);
         self.pixels[index].xyz += Xyza::from(pixel.weighted_spectrum_sum);
         self.pixels[index].filter_weight_sum += pixel.filter_weight_sum;
+        self.pixels[index].statistics.merge(&pixel.statistics);
+        self.channels.merge(
+            self.channels
+                .pixel_states_mut(&mut self.channel_states, index),
+            channel_states,
+        );
     }
 
     /// Get the index into `pixels` of the pixel with the given top-left corner
@@ -183,6 +518,9 @@ struct FilmPixel {
     xyz: Xyza,
 
     filter_weight_sum: f32,
+
+    /// Statistics about the samples taken in the pixel.
+    statistics: PixelStatistics,
 }
 
 impl Default for FilmPixel {
@@ -190,6 +528,137 @@ impl Default for FilmPixel {
         Self {
             xyz: Xyza::transparent(),
             filter_weight_sum: 0.0,
+            statistics: PixelStatistics::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod film_tests {
+    use super::{
+        AlphaMode, Aov, AovValue, BilateralDenoiser, Channel, ColorEncoding, Film, FilmTile,
+        Reconstruction, VisibleSurface,
+    };
+    use crate::{
+        color::{RgbaSpectrum, Xyza},
+        filter::{BoxFilter, FilterTable},
+    };
+    use cgmath::{vec2, Point2, Vector3};
+
+    /// Return a 1x1 film whose pixel is half covered by a surface with the
+    /// given linear gray value.
+    fn half_covered_film(value: f32) -> Film {
+        let mut film = Film::new(vec2(1, 1));
+        film.pixels[0].xyz = Xyza::from(0.5 * RgbaSpectrum::constant(value));
+        film.pixels[0].filter_weight_sum = 1.0;
+        film
+    }
+
+    #[test]
+    fn write_image_encodes_colors() {
+        // Linear 0.2 is about 0.48 in sRGB, and alpha is never encoded.
+        let image = half_covered_film(0.2).write_image();
+        assert_eq!(image.get_pixel(0, 0).0, [124, 124, 124, 128]);
+
+        let image = half_covered_film(0.2)
+            .with_color_encoding(ColorEncoding::Linear)
+            .write_image();
+        assert_eq!(image.get_pixel(0, 0).0, [51, 51, 51, 128]);
+
+        let image = half_covered_film(0.2)
+            .with_color_encoding(ColorEncoding::Linear)
+            .with_alpha_mode(AlphaMode::Premultiplied)
+            .write_image();
+        assert_eq!(image.get_pixel(0, 0).0, [25, 25, 25, 128]);
+
+        let image = half_covered_film(0.2)
+            .with_color_encoding(ColorEncoding::Linear)
+            .write_image_16();
+        assert_eq!(image.get_pixel(0, 0).0, [13107, 13107, 13107, 32768]);
+    }
+
+    #[test]
+    fn channels_reconstruct_aovs() {
+        let mut film = Film::new(vec2(2, 1))
+            .with_channel(Channel::new("shadow", 1, Reconstruction::Filtered))
+            .with_channel(Channel::new("x", 1, Reconstruction::Nearest).with_missing_value(-1.0));
+        let mut tile =
+            FilmTile::new(film.pixel_bounds, f32::INFINITY).with_channels(&film.channels);
+        let filter = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        let radiance = RgbaSpectrum::from_rgb(1.0, 1.0, 1.0);
+        for (x, shadow) in [(0.2, 1.0), (0.6, 0.0), (0.9, 0.5)].iter().copied() {
+            let aovs = [
+                Aov {
+                    name: "shadow",
+                    value: AovValue::Scalar(shadow),
+                },
+                Aov {
+                    name: "x",
+                    value: AovValue::Scalar(x),
+                },
+            ];
+            tile.add_sample(&Point2::new(x, 0.5), &radiance, 1.0, &filter, None, &aovs);
+        }
+        film.merge_tile(&tile);
+
+        let shadow = film.write_scalar_channel("shadow").unwrap();
+        assert_eq!(shadow.get_pixel(0, 0).0, [0.5]);
+        assert_eq!(shadow.get_pixel(1, 0).0, [0.0]);
+        let x = film.write_scalar_channel("x").unwrap();
+        assert_eq!(x.get_pixel(0, 0).0, [0.6]);
+        assert_eq!(x.get_pixel(1, 0).0, [-1.0]);
+        assert!(film.write_vector_channel("x").is_none());
+        assert!(film.write_id_channel("x").is_none());
+    }
+
+    #[test]
+    fn denoise_blurs_noise_within_surfaces() {
+        let mut film = Film::new(vec2(8, 4))
+            .with_channel(Channel::depth())
+            .with_channel(Channel::normal())
+            .with_channel(Channel::albedo());
+        let mut tile =
+            FilmTile::new(film.pixel_bounds, f32::INFINITY).with_channels(&film.channels);
+        let filter = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        // The left and right halves of the film are surfaces that face in
+        // different directions, and their pixels alternate between being too
+        // bright and too dark.
+        let (left, right) = (0.2, 0.8);
+        for pixel in film.pixel_bounds.range() {
+            let noise = if (pixel.x + pixel.y) % 2 == 0 {
+                0.05
+            } else {
+                -0.05
+            };
+            let (value, normal) = if pixel.x < 4 {
+                (left + noise, Vector3::unit_x())
+            } else {
+                (right + noise, Vector3::unit_y())
+            };
+            let visible_surface = VisibleSurface {
+                depth: 1.0,
+                normal,
+                albedo: RgbaSpectrum::constant(1.0),
+                triangle_index: 0,
+                mesh_index: 0,
+            };
+            tile.add_sample(
+                &Point2::new(pixel.x as f32 + 0.5, pixel.y as f32 + 0.5),
+                &RgbaSpectrum::constant(value),
+                1.0,
+                &filter,
+                Some(&visible_surface),
+                &[],
+            );
+        }
+        film.merge_tile(&tile);
+
+        film.denoise(&BilateralDenoiser::new(1.0));
+        let image = film.write_hdr();
+        for (x, _, pixel) in image.enumerate_pixels() {
+            let expected = if x < 4 { left } else { right };
+            assert!(pixel.0[..3].iter().all(|c| (c - expected).abs() < 0.02));
+            assert!((pixel.0[3] - 1.0).abs() < 1e-6);
         }
     }
 }
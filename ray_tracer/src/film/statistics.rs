@@ -0,0 +1,133 @@
+use crate::color::RgbaSpectrum;
+
+/// Running statistics about the luminance of the samples taken in a pixel,
+/// which are used to estimate how far the pixel's value is from converging.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PixelStatistics {
+    /// The number of samples taken in the pixel, not counting samples whose
+    /// radiance wasn't finite.
+    pub sample_count: u32,
+
+    /// The sum of the luminance of each sample.
+    pub luminance_sum: f32,
+
+    /// The sum of the square of the luminance of each sample.
+    pub luminance_square_sum: f32,
+
+    /// The number of samples taken in the pixel whose radiance had a NaN or
+    /// infinite component. These are left out of the other statistics.
+    pub non_finite_sample_count: u32,
+}
+
+impl PixelStatistics {
+    /// Add a sample with the given radiance to the statistics.
+    pub fn add_sample(&mut self, radiance: &RgbaSpectrum) {
+        if radiance.has_non_finite() {
+            self.non_finite_sample_count += 1;
+            return;
+        }
+        let luminance = radiance.luminance();
+        self.sample_count += 1;
+        self.luminance_sum += luminance;
+        self.luminance_square_sum += luminance * luminance;
+    }
+
+    /// Add the samples counted in `other` to the statistics.
+    pub fn merge(&mut self, other: &Self) {
+        self.sample_count += other.sample_count;
+        self.luminance_sum += other.luminance_sum;
+        self.luminance_square_sum += other.luminance_square_sum;
+        self.non_finite_sample_count += other.non_finite_sample_count;
+    }
+
+    /// Return the mean luminance of the samples, or zero if there are none.
+    pub fn mean(&self) -> f32 {
+        if self.sample_count == 0 {
+            0.0
+        } else {
+            self.luminance_sum / self.sample_count as f32
+        }
+    }
+
+    /// Return the unbiased sample variance of the samples' luminance, or
+    /// `None` if fewer than two samples have been taken.
+    pub fn variance(&self) -> Option<f32> {
+        if self.sample_count < 2 {
+            return None;
+        }
+        let n = self.sample_count as f32;
+        let variance = (self.luminance_square_sum - self.luminance_sum * self.mean()) / (n - 1.0);
+        // Rounding error can make the variance of nearly identical samples
+        // slightly negative.
+        Some(variance.max(0.0))
+    }
+
+    /// Return an estimate of the error in the pixel's mean luminance relative
+    /// to the mean luminance: the standard error of the mean, divided by the
+    /// mean. Dark pixels are compared to a luminance of `MIN_LUMINANCE` instead
+    /// of their mean, so that tiny absolute errors don't count as large
+    /// relative errors.
+    ///
+    /// Returns infinity if fewer than two samples have been taken, since the
+    /// error can't be estimated yet.
+    pub fn relative_error(&self) -> f32 {
+        const MIN_LUMINANCE: f32 = 0.01;
+        match self.variance() {
+            Some(variance) => {
+                let standard_error = (variance / self.sample_count as f32).sqrt();
+                standard_error / self.mean().max(MIN_LUMINANCE)
+            }
+            None => f32::INFINITY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod pixel_statistics_tests {
+    use super::PixelStatistics;
+    use crate::color::RgbaSpectrum;
+
+    #[test]
+    fn identical_samples_have_no_error() {
+        let mut statistics = PixelStatistics::default();
+        statistics.add_sample(&RgbaSpectrum::from_rgb(0.3, 0.6, 0.2));
+        assert_eq!(statistics.relative_error(), f32::INFINITY);
+
+        for _ in 0..3 {
+            statistics.add_sample(&RgbaSpectrum::from_rgb(0.3, 0.6, 0.2));
+        }
+        assert_eq!(statistics.sample_count, 4);
+        assert!(statistics.relative_error() < 1e-3);
+    }
+
+    #[test]
+    fn error_shrinks_with_more_samples() {
+        let black = RgbaSpectrum::black();
+        let white = RgbaSpectrum::from_rgb(1.0, 1.0, 1.0);
+
+        let mut few = PixelStatistics::default();
+        for radiance in [&black, &white, &black, &white] {
+            few.add_sample(radiance);
+        }
+        let mut many = few;
+        many.merge(&few);
+        many.merge(&few);
+
+        assert!((few.mean() - 0.5).abs() < 1e-3);
+        assert!((few.variance().unwrap() - 1.0 / 3.0).abs() < 1e-3);
+        assert!(many.relative_error() < few.relative_error());
+    }
+
+    #[test]
+    fn non_finite_samples_are_counted_separately() {
+        let mut statistics = PixelStatistics::default();
+        statistics.add_sample(&RgbaSpectrum::from_rgb(0.5, 0.5, 0.5));
+        statistics.add_sample(&RgbaSpectrum::from_rgb(f32::NAN, 0.5, 0.5));
+        statistics.add_sample(&RgbaSpectrum::from_rgb(0.5, f32::INFINITY, 0.5));
+        statistics.add_sample(&RgbaSpectrum::from_rgb(0.5, 0.5, 0.5));
+
+        assert_eq!(statistics.sample_count, 2);
+        assert_eq!(statistics.non_finite_sample_count, 2);
+        assert_eq!(statistics.variance(), Some(0.0));
+    }
+}
@@ -1,6 +1,10 @@
-use cgmath::{Point2, Vector2};
+use cgmath::{InnerSpace, Point2, Vector2};
 
-use crate::{color::RgbaSpectrum, filter::Filter, geometry::bounds::Bounds2};
+use super::{
+    channel::{Aov, AovValue, ChannelLayout, ChannelStates, PixelStates},
+    PixelStatistics, VisibleSurface,
+};
+use crate::{color::RgbaSpectrum, filter::FilterTable, geometry::bounds::Bounds2};
 
 /// Stores the pixel data for a subset of a larger `Film`. A `FilmTile` can be
 /// merged into a `Film` to add its pixel contributions to the `Film`.
@@ -10,18 +14,46 @@ pub struct FilmTile {
     pub pixel_bounds: Bounds2<i32>,
 
     pub pixels: Vec<FilmTilePixel>,
+
+    /// The film's auxiliary channels, and the state of each of them in each
+    /// pixel, laid out one pixel after another in the same order as `pixels`.
+    channels: ChannelLayout,
+    channel_states: ChannelStates,
+
+    /// Samples whose luminance is greater than this are scaled down to this
+    /// luminance before they're added to the tile.
+    max_sample_luminance: f32,
 }
 
 impl FilmTile {
-    pub fn new(pixel_bounds: Bounds2<i32>) -> Self {
+    pub fn new(pixel_bounds: Bounds2<i32>, max_sample_luminance: f32) -> Self {
         let pixels = vec![FilmTilePixel::default(); pixel_bounds.area().max(0) as usize];
         Self {
             pixel_bounds,
             pixels,
+            channels: ChannelLayout::default(),
+            channel_states: ChannelStates::default(),
+            max_sample_luminance,
         }
     }
 
-    /// Add the radiance from a sample to the pixels in the tile.
+    /// Return the tile, recording the given auxiliary channels.
+    pub(super) fn with_channels(mut self, channels: &ChannelLayout) -> Self {
+        self.channel_states = channels.initial_states(self.pixels.len());
+        self.channels = channels.clone();
+        self
+    }
+
+    /// Add the radiance from a sample to the pixels in the tile, and add the
+    /// sample to the statistics of the pixel that it was taken in. The
+    /// sample's auxiliary values are added to the tile's channels according to
+    /// their reconstruction rules.
+    ///
+    /// Samples whose radiance has a NaN or infinite component would make every
+    /// pixel they contribute to NaN or infinite, so they're dropped, and only
+    /// counted in the statistics of the pixel that they were taken in. Samples
+    /// that are brighter than the tile's maximum sample luminance are scaled
+    /// down to it.
     ///
     /// * sample_film_point - Location of the sample on the film in raster
     ///   space.
@@ -30,14 +62,62 @@ impl FilmTile {
     /// * sample_weight - Weight that indicates how much the radiance from the
     ///   given sample contributes to the final image relative to the radiance
     ///   from other samples.
-    /// * filter - The filter used to reconstruct pixels from various samples.
+    /// * filter - A table of the filter used to reconstruct pixels from
+    ///   various samples.
+    /// * visible_surface - The surface that the sample's camera ray hit first,
+    ///   if any, which has the sample's values for the standard channels.
+    /// * aovs - The sample's values for any other channels.
     pub fn add_sample(
         &mut self,
         sample_film_point: &Point2<f32>,
         radiance: &RgbaSpectrum,
         sample_weight: f32,
-        filter: &dyn Filter,
+        filter: &FilterTable,
+        visible_surface: Option<&VisibleSurface>,
+        aovs: &[Aov],
     ) {
+        let aov = |name: &str| -> Option<AovValue> {
+            aovs.iter()
+                .find(|aov| aov.name == name)
+                .map(|aov| aov.value)
+                .or_else(|| visible_surface.and_then(|surface| surface.aov(name)))
+        };
+
+        let luminance = radiance.luminance();
+        let radiance = &if luminance > self.max_sample_luminance {
+            // Scale the color but not the alpha.
+            let scale = self.max_sample_luminance / luminance;
+            RgbaSpectrum::from_rgba(
+                scale * radiance.r(),
+                scale * radiance.g(),
+                scale * radiance.b(),
+                radiance.a(),
+            )
+        } else {
+            *radiance
+        };
+
+        let sample_pixel = Point2::new(
+            sample_film_point.x.floor() as i32,
+            sample_film_point.y.floor() as i32,
+        );
+        if self.contains_pixel(&sample_pixel) {
+            let index = self.pixel_index(&sample_pixel);
+            self.pixels[index].statistics.add_sample(radiance);
+            let center_offset = sample_film_point
+                - Point2::new(sample_pixel.x as f32 + 0.5, sample_pixel.y as f32 + 0.5);
+            self.channels.add_unfiltered(
+                self.channels
+                    .pixel_states_mut(&mut self.channel_states, index),
+                aov,
+                center_offset.magnitude2(),
+                visible_surface.map_or(f32::INFINITY, |surface| surface.depth),
+            );
+        }
+        if radiance.has_non_finite() {
+            return;
+        }
+
         if let Some(bounds) = self.pixel_bounds_for_sample_point(
             sample_film_point,
             filter.half_width(),
@@ -45,15 +125,24 @@ impl FilmTile {
         ) {
             // Loop through each pixel that the sample might contribute to.
             for pixel_min_corner in bounds.range() {
-                // Find the position of the sample relative to the min corner of
+                // Find the position of the sample relative to the center of
                 // the pixel.
                 let sample_offset = sample_film_point
-                    - Point2::new(pixel_min_corner.x as f32, pixel_min_corner.y as f32);
-                let filter_weight = filter.eval_at(Point2::new(0.0, 0.0) + sample_offset);
+                    - Point2::new(
+                        pixel_min_corner.x as f32 + 0.5,
+                        pixel_min_corner.y as f32 + 0.5,
+                    );
+                let filter_weight = filter.weight(sample_offset);
                 let index = self.pixel_index(&pixel_min_corner);
                 self.pixels[index].filter_weight_sum += filter_weight;
                 self.pixels[index].weighted_spectrum_sum +=
                     filter_weight * sample_weight * radiance;
+                self.channels.add_filtered(
+                    self.channels
+                        .pixel_states_mut(&mut self.channel_states, index),
+                    aov,
+                    filter_weight,
+                );
             }
         }
     }
@@ -64,6 +153,13 @@ impl FilmTile {
         self.pixels.get(index)
     }
 
+    /// Get the states of the auxiliary channels of the pixel whose top-left
+    /// corner is at the given point.
+    pub(super) fn channel_states_at(&self, pixel_min_corner: Point2<i32>) -> PixelStates<'_> {
+        let index = self.pixel_index(&pixel_min_corner);
+        self.channels.pixel_states(&self.channel_states, index)
+    }
+
     /// Return a bounding box around the pixels (in raster space) that a sample
     /// at `sample_point` could contribute to.
     ///
@@ -89,6 +185,15 @@ impl FilmTile {
         possible_pixel_bounds.intersect(&self.pixel_bounds)
     }
 
+    /// Return `true` if the pixel with the given top-left corner is in the
+    /// tile.
+    fn contains_pixel(&self, p: &Point2<i32>) -> bool {
+        p.x >= self.pixel_bounds.min.x
+            && p.x < self.pixel_bounds.max.x
+            && p.y >= self.pixel_bounds.min.y
+            && p.y < self.pixel_bounds.max.y
+    }
+
     /// Get the index into `pixels` of the pixel with the given top-left corner
     /// in raster space.
     fn pixel_index(&self, p: &Point2<i32>) -> usize {
@@ -111,6 +216,11 @@ pub struct FilmTilePixel {
     /// p. 490 of PBR ed. 3). This value is the sum of filter values at the
     /// contributing sample points.
     pub filter_weight_sum: f32,
+
+    /// Statistics about the samples taken in the pixel. Unlike the sums
+    /// above, these only include samples whose film points are inside the
+    /// pixel.
+    pub statistics: PixelStatistics,
 }
 
 impl Default for FilmTilePixel {
@@ -118,6 +228,148 @@ impl Default for FilmTilePixel {
         Self {
             weighted_spectrum_sum: RgbaSpectrum::from_rgba(0.0, 0.0, 0.0, 0.0),
             filter_weight_sum: 0.0,
+            statistics: PixelStatistics::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod film_tile_tests {
+    use super::FilmTile;
+    use crate::{
+        color::RgbaSpectrum,
+        film::{Aov, AovValue, Channel, Film, Reconstruction, VisibleSurface, NO_INDEX},
+        filter::{BoxFilter, FilterTable, MitchellFilter},
+        geometry::bounds::Bounds2,
+    };
+    use cgmath::{vec2, Matrix4, Point2, SquareMatrix, Vector3};
+
+    #[test]
+    fn box_filtered_samples_only_reach_their_pixel() {
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 4));
+        let mut tile = FilmTile::new(bounds, f32::INFINITY);
+        let filter = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        let radiance = RgbaSpectrum::from_rgb(1.0, 1.0, 1.0);
+        for sample in [Point2::new(1.1, 2.2), Point2::new(1.9, 2.8)] {
+            tile.add_sample(&sample, &radiance, 1.0, &filter, None, &[]);
+        }
+
+        for pixel in bounds.range() {
+            let filter_weight_sum = tile.pixel_at(pixel).unwrap().filter_weight_sum;
+            if pixel == Point2::new(1, 2) {
+                assert_eq!(filter_weight_sum, 2.0);
+            } else {
+                assert_eq!(filter_weight_sum, 0.0);
+            }
+        }
+    }
+
+    #[test]
+    fn non_finite_samples_are_dropped() {
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 4));
+        let mut tile = FilmTile::new(bounds, f32::INFINITY);
+        let filter = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        let radiance = RgbaSpectrum::from_rgb(f32::NAN, 1.0, 1.0);
+        tile.add_sample(&Point2::new(1.5, 2.5), &radiance, 1.0, &filter, None, &[]);
+
+        let pixel = tile.pixel_at(Point2::new(1, 2)).unwrap();
+        assert_eq!(pixel.filter_weight_sum, 0.0);
+        assert!(!pixel.weighted_spectrum_sum.has_nan());
+        assert_eq!(pixel.statistics.non_finite_sample_count, 1);
+    }
+
+    #[test]
+    fn bright_samples_are_clamped() {
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 4));
+        let mut tile = FilmTile::new(bounds, 2.0);
+        let filter = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        let radiance = RgbaSpectrum::from_rgba(100.0, 50.0, 10.0, 0.5);
+        tile.add_sample(&Point2::new(1.5, 2.5), &radiance, 1.0, &filter, None, &[]);
+
+        let clamped = tile
+            .pixel_at(Point2::new(1, 2))
+            .unwrap()
+            .weighted_spectrum_sum;
+        assert!((clamped.luminance() - 2.0).abs() < 1e-4);
+        assert!((clamped.r() / clamped.g() - 2.0).abs() < 1e-4);
+        assert_eq!(clamped.a(), 0.5);
+    }
+
+    #[test]
+    fn pixels_record_the_nearest_surface_in_them() {
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 4));
+        let mut film = Film::new(vec2(4, 4))
+            .with_channel(Channel::depth())
+            .with_channel(Channel::normal())
+            .with_channel(Channel::triangle_index());
+        let mut tile = FilmTile::new(bounds, f32::INFINITY).with_channels(&film.channels);
+        let filter = FilterTable::new(&MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0));
+        let radiance = RgbaSpectrum::from_rgb(1.0, 1.0, 1.0);
+        for (triangle_index, (depth, normal)) in [
+            (3.0, Vector3::unit_x()),
+            (2.0, Vector3::unit_y()),
+            (4.0, Vector3::unit_z()),
+        ]
+        .iter()
+        .copied()
+        .enumerate()
+        {
+            let visible_surface = VisibleSurface {
+                depth,
+                normal,
+                albedo: radiance,
+                triangle_index,
+                mesh_index: 0,
+            };
+            tile.add_sample(
+                &Point2::new(1.5, 2.5),
+                &radiance,
+                1.0,
+                &filter,
+                Some(&visible_surface),
+                &[],
+            );
+        }
+        tile.add_sample(&Point2::new(1.5, 2.5), &radiance, 1.0, &filter, None, &[]);
+        assert!(tile.pixel_at(Point2::new(2, 2)).unwrap().filter_weight_sum > 0.0);
+        film.merge_tile(&tile);
+
+        // The samples' radiance reaches the neighboring pixels, but their
+        // surfaces don't.
+        let depth = film.write_depth().unwrap();
+        let normals = film.write_normals(&Matrix4::identity()).unwrap();
+        let triangle_indices = film.write_triangle_indices().unwrap();
+        assert_eq!(depth.get_pixel(1, 2).0, [2.0]);
+        assert_eq!(normals.get_pixel(1, 2).0, [0.0, 1.0, 0.0]);
+        assert_eq!(triangle_indices.get_pixel(1, 2).0, [1]);
+        assert_eq!(depth.get_pixel(2, 2).0, [f32::INFINITY]);
+        assert_eq!(normals.get_pixel(2, 2).0, [0.0; 3]);
+        assert_eq!(triangle_indices.get_pixel(2, 2).0, [NO_INDEX]);
+    }
+
+    #[test]
+    fn id_channels_keep_ids_exactly() {
+        let bounds = Bounds2::new(Point2::new(0, 0), Point2::new(4, 1));
+        let mut film =
+            Film::new(vec2(4, 1)).with_channel(Channel::new("id", 1, Reconstruction::Id));
+        let mut tile = FilmTile::new(bounds, f32::INFINITY).with_channels(&film.channels);
+        let filter = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        let radiance = RgbaSpectrum::from_rgb(1.0, 1.0, 1.0);
+        // The bits of a signaling NaN, the first integer that a float can't
+        // hold, and the largest ID that isn't `NO_INDEX`.
+        let ids = [0x7f80_0001, (1 << 24) + 1, NO_INDEX - 1];
+        for (x, id) in ids.iter().enumerate() {
+            let aov = Aov {
+                name: "id",
+                value: AovValue::Id(*id),
+            };
+            let sample = Point2::new(x as f32 + 0.5, 0.5);
+            tile.add_sample(&sample, &radiance, 1.0, &filter, None, &[aov]);
+        }
+        film.merge_tile(&tile);
+
+        let written_ids = film.write_id_channel("id").unwrap().into_raw();
+        assert_eq!(written_ids, [ids[0], ids[1], ids[2], NO_INDEX]);
+        assert!(film.write_scalar_channel("id").is_none());
+    }
+}
@@ -0,0 +1,55 @@
+/// An operator that maps linear radiance, which can be arbitrarily bright, to
+/// the [0, 1] range of a display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneMap {
+    /// Clamp values to [0, 1]. Values brighter than 1 are blown out to white.
+    #[default]
+    Clamp,
+
+    /// Map each value `v` to `v / (1 + v)`, the simple operator from Reinhard
+    /// et al.'s "Photographic Tone Reproduction for Digital Images". Bright
+    /// values are compressed smoothly, but dark values are darkened too.
+    Reinhard,
+
+    /// Krzysztof Narkowicz's fit of the ACES filmic tone curve, which keeps
+    /// contrast in the midtones and rolls highlights off to white like film.
+    AcesFilmic,
+}
+
+impl ToneMap {
+    /// Map a linear value to [0, 1].
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = value.max(0.0);
+        match self {
+            Self::Clamp => value.min(1.0),
+            Self::Reinhard => value / (1.0 + value),
+            Self::AcesFilmic => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                ((value * (a * value + b)) / (value * (c * value + d) + e)).clamp(0.0, 1.0)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tone_map_tests {
+    use super::ToneMap;
+
+    #[test]
+    fn maps_every_value_into_the_unit_interval() {
+        for tone_map in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::AcesFilmic] {
+            assert_eq!(tone_map.apply(-1.0), 0.0);
+            assert_eq!(tone_map.apply(0.0), 0.0);
+
+            let mut previous = 0.0;
+            for value in [0.01, 0.1, 0.5, 1.0, 2.0, 10.0, 1000.0] {
+                let mapped = tone_map.apply(value);
+                assert!((0.0..=1.0).contains(&mapped));
+                assert!(mapped >= previous);
+                previous = mapped;
+            }
+        }
+        assert_eq!(ToneMap::Clamp.apply(0.5), 0.5);
+        assert_eq!(ToneMap::Reinhard.apply(1.0), 0.5);
+    }
+}
@@ -0,0 +1,73 @@
+use super::channel::{self, AovValue};
+use crate::{color::RgbaSpectrum, interaction::SurfaceInteraction};
+use cgmath::Vector3;
+use std::convert::TryFrom;
+
+/// The surface that a camera ray hits first, which the film records in its
+/// standard channels alongside the radiance, like the `VisibleSurface` of PBR
+/// ed. 4.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VisibleSurface {
+    /// The parametric distance along the camera ray to the surface. Camera
+    /// rays start at the near clipping plane and have unit length directions,
+    /// so this is the distance of the surface from the near clipping plane.
+    pub depth: f32,
+
+    /// The unit length geometric normal of the surface in world space.
+    pub normal: Vector3<f32>,
+
+    /// The color of the surface's material, without any lighting.
+    pub albedo: RgbaSpectrum,
+
+    /// The index of the triangle in its mesh, as in
+    /// `mesh::Triangle::index_in_mesh`.
+    pub triangle_index: usize,
+
+    /// The index of the triangle's mesh among the meshes in the scene.
+    pub mesh_index: usize,
+}
+
+impl VisibleSurface {
+    /// Describe the surface of an interaction that's `t` along a camera ray.
+    ///
+    /// * t - The parametric distance along the camera ray to the interaction.
+    /// * interaction - The interaction with the surface.
+    /// * albedo - The color of the surface's material.
+    /// * triangle_index - The index of the triangle that was hit in its mesh.
+    /// * mesh_index - The index of the triangle's mesh in the scene.
+    pub(crate) fn new(
+        t: f32,
+        interaction: &SurfaceInteraction,
+        albedo: RgbaSpectrum,
+        triangle_index: usize,
+        mesh_index: usize,
+    ) -> Self {
+        Self {
+            depth: t,
+            normal: interaction.original_geometry.normal,
+            albedo,
+            triangle_index,
+            mesh_index,
+        }
+    }
+
+    /// Return the surface's value for the standard channel with the given
+    /// name, if it is one. Indices that don't fit in an ID are left out.
+    pub(super) fn aov(&self, name: &str) -> Option<AovValue> {
+        match name {
+            channel::DEPTH => Some(AovValue::Scalar(self.depth)),
+            channel::NORMAL => Some(AovValue::Vector(self.normal)),
+            channel::ALBEDO => Some(AovValue::Color(self.albedo)),
+            channel::TRIANGLE_INDEX => id(self.triangle_index),
+            channel::MESH_INDEX => id(self.mesh_index),
+            _ => None,
+        }
+    }
+}
+
+fn id(index: usize) -> Option<AovValue> {
+    u32::try_from(index)
+        .ok()
+        .filter(|&id| id != channel::NO_INDEX)
+        .map(AovValue::Id)
+}
@@ -0,0 +1,54 @@
+use super::Filter;
+use cgmath::Point2;
+
+/// A filter that weights every sample within its bounds equally. With a
+/// half-width and half-height of 0.5, each pixel is the average of the
+/// samples inside the pixel, so pixels don't blur into their neighbors.
+pub struct BoxFilter {
+    half_width: f32,
+    half_height: f32,
+}
+
+impl BoxFilter {
+    pub fn new(half_width: f32, half_height: f32) -> Self {
+        Self {
+            half_width,
+            half_height,
+        }
+    }
+}
+
+impl Filter for BoxFilter {
+    fn eval_at(&self, p: Point2<f32>) -> f32 {
+        if p.x.abs() <= self.half_width && p.y.abs() <= self.half_height {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn half_width(&self) -> f32 {
+        self.half_width
+    }
+
+    fn half_height(&self) -> f32 {
+        self.half_height
+    }
+}
+
+#[cfg(test)]
+mod box_filter_tests {
+    use super::BoxFilter;
+    use crate::filter::Filter;
+    use cgmath::Point2;
+
+    #[test]
+    fn weights_samples_within_its_bounds_equally() {
+        let filter = BoxFilter::new(1.5, 0.5);
+        assert_eq!(filter.eval_at(Point2::new(0.0, 0.0)), 1.0);
+        assert_eq!(filter.eval_at(Point2::new(-1.2, 0.3)), 1.0);
+        assert_eq!(filter.eval_at(Point2::new(1.5, -0.5)), 1.0);
+        assert_eq!(filter.eval_at(Point2::new(1.6, 0.0)), 0.0);
+        assert_eq!(filter.eval_at(Point2::new(0.0, -0.6)), 0.0);
+    }
+}
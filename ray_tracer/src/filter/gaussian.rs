@@ -0,0 +1,54 @@
+use super::Filter;
+use cgmath::Point2;
+
+/// A filter that weights samples with a Gaussian bump, which gives a smooth
+/// but slightly blurry image (PBR ed. 3, section 7.8.3).
+///
+/// The Gaussian is shifted down by its value at the edge of the filter's
+/// bounds, so that the weight falls to zero at the edge.
+pub struct GaussianFilter {
+    half_width: f32,
+    half_height: f32,
+
+    /// The falloff rate of the Gaussian. Smaller values fall off more
+    /// slowly, which gives a blurrier image.
+    alpha: f32,
+
+    /// The value of the Gaussian at the edges of the filter's bounds.
+    exp_x: f32,
+    exp_y: f32,
+}
+
+impl GaussianFilter {
+    pub fn new(half_width: f32, half_height: f32, alpha: f32) -> Self {
+        Self {
+            half_width,
+            half_height,
+            alpha,
+            exp_x: (-alpha * half_width * half_width).exp(),
+            exp_y: (-alpha * half_height * half_height).exp(),
+        }
+    }
+
+    fn gaussian(&self, d: f32, exp_v: f32) -> f32 {
+        ((-self.alpha * d * d).exp() - exp_v).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn eval_at(&self, p: Point2<f32>) -> f32 {
+        if p.x.abs() <= self.half_width && p.y.abs() <= self.half_height {
+            self.gaussian(p.x, self.exp_x) * self.gaussian(p.y, self.exp_y)
+        } else {
+            0.0
+        }
+    }
+
+    fn half_width(&self) -> f32 {
+        self.half_width
+    }
+
+    fn half_height(&self) -> f32 {
+        self.half_height
+    }
+}
@@ -0,0 +1,88 @@
+use super::Filter;
+use cgmath::Point2;
+use std::f32::consts::PI;
+
+/// A filter based on the sinc function, which is the ideal reconstruction
+/// filter, windowed by a stretched copy of itself so that it falls to zero at
+/// the edges of the filter's bounds (PBR ed. 3, section 7.8.5).
+///
+/// The filter keeps the image sharp, at the cost of some ringing near sharp
+/// edges.
+pub struct LanczosSincFilter {
+    half_width: f32,
+    half_height: f32,
+
+    /// The number of cycles that the sinc function passes through before it
+    /// is clamped to zero.
+    tau: f32,
+}
+
+impl LanczosSincFilter {
+    pub fn new(half_width: f32, half_height: f32, tau: f32) -> Self {
+        Self {
+            half_width,
+            half_height,
+            tau,
+        }
+    }
+
+    fn windowed_sinc(&self, x: f32, radius: f32) -> f32 {
+        let x = x.abs();
+        if x > radius {
+            0.0
+        } else {
+            sinc(x) * sinc(x / self.tau)
+        }
+    }
+}
+
+/// Return `sin(πx) / (πx)`, which is 1 at 0.
+fn sinc(x: f32) -> f32 {
+    let x = x.abs();
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl Filter for LanczosSincFilter {
+    fn eval_at(&self, p: Point2<f32>) -> f32 {
+        self.windowed_sinc(p.x, self.half_width) * self.windowed_sinc(p.y, self.half_height)
+    }
+
+    fn half_width(&self) -> f32 {
+        self.half_width
+    }
+
+    fn half_height(&self) -> f32 {
+        self.half_height
+    }
+}
+
+#[cfg(test)]
+mod lanczos_sinc_filter_tests {
+    use super::LanczosSincFilter;
+    use crate::filter::Filter;
+    use cgmath::Point2;
+
+    #[test]
+    fn is_one_at_the_center_and_zero_at_the_edges() {
+        let filter = LanczosSincFilter::new(4.0, 2.0, 3.0);
+        assert_eq!(filter.eval_at(Point2::new(0.0, 0.0)), 1.0);
+        assert!(filter.eval_at(Point2::new(4.0, 0.0)).abs() < 1e-6);
+        assert!(filter.eval_at(Point2::new(0.0, -2.0)).abs() < 1e-6);
+        assert_eq!(filter.eval_at(Point2::new(4.5, 0.0)), 0.0);
+        assert_eq!(filter.eval_at(Point2::new(0.0, 2.5)), 0.0);
+    }
+
+    #[test]
+    fn has_negative_lobes() {
+        let filter = LanczosSincFilter::new(4.0, 4.0, 3.0);
+        // sinc(1.5) * sinc(0.5) is about -0.135.
+        let weight = filter.eval_at(Point2::new(1.5, 0.0));
+        assert!((weight + 0.135).abs() < 1e-3, "{}", weight);
+        assert!(filter.eval_at(Point2::new(0.0, -1.5)) < 0.0);
+        assert!(filter.eval_at(Point2::new(2.5, 0.0)) > 0.0);
+    }
+}
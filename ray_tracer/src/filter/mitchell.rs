@@ -24,6 +24,7 @@ impl MitchellFilter {
         }
     }
 
+    #[allow(clippy::neg_multiply)]
     fn mitchell_1d(&self, x: f32) -> f32 {
         let x = (2.0 * x).abs();
         if x > 1.0 {
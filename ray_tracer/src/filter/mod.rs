@@ -1,6 +1,16 @@
+mod box_filter;
+mod gaussian;
+mod lanczos_sinc;
 mod mitchell;
+mod table;
+mod triangle;
 
+pub use box_filter::BoxFilter;
+pub use gaussian::GaussianFilter;
+pub use lanczos_sinc::LanczosSincFilter;
 pub use mitchell::MitchellFilter;
+pub use table::FilterTable;
+pub use triangle::TriangleFilter;
 
 use cgmath::Point2;
 
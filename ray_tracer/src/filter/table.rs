@@ -0,0 +1,99 @@
+use super::Filter;
+use cgmath::{Point2, Vector2};
+
+/// The number of entries in each row and column of a `FilterTable`.
+const FILTER_TABLE_WIDTH: usize = 16;
+
+/// A table of a filter's values over one quadrant of the filter's bounds, which
+/// are looked up in place of evaluating the filter for every pixel that every
+/// sample contributes to (PBR ed. 3, section 7.9).
+///
+/// Filters are assumed to be symmetric about both axes, so the table only
+/// covers offsets with non-negative coordinates, and the value at each offset
+/// is approximated by the value at the center of the table entry that contains
+/// it.
+pub struct FilterTable {
+    half_width: f32,
+    half_height: f32,
+
+    /// Filter values in row-major order. The entry in row `y` and column `x`
+    /// is the value at the center of the entry, which is offset from the
+    /// filter's center by `(x + 0.5) / FILTER_TABLE_WIDTH` of the half-width
+    /// and `(y + 0.5) / FILTER_TABLE_WIDTH` of the half-height.
+    weights: Vec<f32>,
+}
+
+impl FilterTable {
+    pub fn new(filter: &dyn Filter) -> Self {
+        let half_width = filter.half_width();
+        let half_height = filter.half_height();
+        let weights = (0..FILTER_TABLE_WIDTH)
+            .flat_map(|y| (0..FILTER_TABLE_WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                filter.eval_at(Point2::new(
+                    (x as f32 + 0.5) / FILTER_TABLE_WIDTH as f32 * half_width,
+                    (y as f32 + 0.5) / FILTER_TABLE_WIDTH as f32 * half_height,
+                ))
+            })
+            .collect();
+        Self {
+            half_width,
+            half_height,
+            weights,
+        }
+    }
+
+    /// Return the filter's weight for a sample at the given offset from the
+    /// center of a pixel.
+    pub fn weight(&self, offset: Vector2<f32>) -> f32 {
+        let x = offset.x.abs();
+        let y = offset.y.abs();
+        if !(x <= self.half_width && y <= self.half_height) {
+            return 0.0;
+        }
+        let entry = |d: f32, half_extent: f32| {
+            ((d / half_extent * FILTER_TABLE_WIDTH as f32) as usize).min(FILTER_TABLE_WIDTH - 1)
+        };
+        self.weights[entry(y, self.half_height) * FILTER_TABLE_WIDTH + entry(x, self.half_width)]
+    }
+
+    /// Return half the width of the filter.
+    pub fn half_width(&self) -> f32 {
+        self.half_width
+    }
+
+    /// Return half the height of the filter.
+    pub fn half_height(&self) -> f32 {
+        self.half_height
+    }
+}
+
+#[cfg(test)]
+mod filter_table_tests {
+    use super::FilterTable;
+    use crate::filter::{Filter, GaussianFilter, MitchellFilter};
+    use cgmath::{Point2, Vector2};
+
+    #[test]
+    fn approximates_the_filter() {
+        let filters: [&dyn Filter; 2] = [
+            &MitchellFilter::new(2.0, 1.5, 1.0 / 3.0, 1.0 / 3.0),
+            &GaussianFilter::new(2.0, 1.5, 2.0),
+        ];
+        for filter in filters {
+            let table = FilterTable::new(filter);
+            for (x, y) in [(0.0, 0.0), (0.3, -0.2), (-1.1, 0.7), (1.9, -1.4)] {
+                let expected = filter.eval_at(Point2::new(x, y));
+                let weight = table.weight(Vector2::new(x, y));
+                assert!(
+                    (weight - expected).abs() < 0.05,
+                    "{} != {}",
+                    weight,
+                    expected
+                );
+            }
+            assert_eq!(table.weight(Vector2::new(2.1, 0.0)), 0.0);
+            assert_eq!(table.weight(Vector2::new(0.0, -1.6)), 0.0);
+        }
+    }
+}
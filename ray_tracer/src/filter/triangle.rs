@@ -0,0 +1,50 @@
+use super::Filter;
+use cgmath::Point2;
+
+/// A filter whose weight falls off linearly from the center to the edges of
+/// its bounds.
+pub struct TriangleFilter {
+    half_width: f32,
+    half_height: f32,
+}
+
+impl TriangleFilter {
+    pub fn new(half_width: f32, half_height: f32) -> Self {
+        Self {
+            half_width,
+            half_height,
+        }
+    }
+}
+
+impl Filter for TriangleFilter {
+    fn eval_at(&self, p: Point2<f32>) -> f32 {
+        (self.half_width - p.x.abs()).max(0.0) * (self.half_height - p.y.abs()).max(0.0)
+    }
+
+    fn half_width(&self) -> f32 {
+        self.half_width
+    }
+
+    fn half_height(&self) -> f32 {
+        self.half_height
+    }
+}
+
+#[cfg(test)]
+mod triangle_filter_tests {
+    use super::TriangleFilter;
+    use crate::filter::Filter;
+    use cgmath::Point2;
+
+    #[test]
+    fn falls_off_linearly_to_the_edges() {
+        let filter = TriangleFilter::new(1.0, 1.0);
+        assert_eq!(filter.eval_at(Point2::new(0.0, 0.0)), 1.0);
+        assert_eq!(filter.eval_at(Point2::new(0.5, 0.0)), 0.5);
+        assert_eq!(filter.eval_at(Point2::new(-0.5, 0.5)), 0.25);
+        assert_eq!(filter.eval_at(Point2::new(1.0, 0.0)), 0.0);
+        assert_eq!(filter.eval_at(Point2::new(0.0, -1.0)), 0.0);
+        assert_eq!(filter.eval_at(Point2::new(1.5, 0.0)), 0.0);
+    }
+}
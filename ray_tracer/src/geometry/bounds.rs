@@ -1,4 +1,7 @@
-use cgmath::{BaseNum, Point2, Vector2};
+use crate::geometry::{axis::Axis3, point, vector};
+use crate::number::efloat;
+use crate::ray::Ray;
+use cgmath::{BaseNum, Point2, Point3, Vector2, Vector3};
 
 #[derive(PartialEq, Eq, Copy, Clone)]
 pub struct Bounds2<S> {
@@ -46,6 +49,50 @@ impl Bounds2<i32> {
     }
 }
 
+impl Bounds2<f32> {
+    /// Return a degenerate bounding box that contains nothing. Adding any point
+    /// to the empty box gives a box around only that point.
+    pub fn empty() -> Self {
+        Self {
+            min: Point2::new(f32::INFINITY, f32::INFINITY),
+            max: Point2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    /// Return the smallest bounding box that contains the bounding box and the
+    /// point.
+    pub fn union_point(&self, p: &Point2<f32>) -> Self {
+        Self {
+            min: Point2::new(self.min.x.min(p.x), self.min.y.min(p.y)),
+            max: Point2::new(self.max.x.max(p.x), self.max.y.max(p.y)),
+        }
+    }
+
+    /// Return the bounding box grown by `dx` on its left and right sides and by
+    /// `dy` on its top and bottom sides.
+    pub fn padded(&self, dx: f32, dy: f32) -> Self {
+        Self {
+            min: Point2::new(self.min.x - dx, self.min.y - dy),
+            max: Point2::new(self.max.x + dx, self.max.y + dy),
+        }
+    }
+
+    /// Return `true` if the point is inside the bounding box or on its
+    /// boundary.
+    pub fn contains(&self, p: &Point2<f32>) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
+    }
+
+    /// Return `true` if the bounding boxes share any points, including points
+    /// on their boundaries.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+}
+
 impl From<Bounds2<i32>> for Bounds2<f32> {
     fn from(bounds: Bounds2<i32>) -> Self {
         Bounds2::new(
@@ -55,6 +102,229 @@ impl From<Bounds2<i32>> for Bounds2<f32> {
     }
 }
 
+/// An axis-aligned bounding box in 3D space.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Bounds3<S> {
+    pub min: Point3<S>,
+    pub max: Point3<S>,
+}
+
+impl<S> Bounds3<S> {
+    pub fn new(min: Point3<S>, max: Point3<S>) -> Self {
+        Self { min, max }
+    }
+}
+
+impl Bounds3<f32> {
+    /// Return a degenerate bounding box that contains nothing. The union of
+    /// the empty box and any other box is the other box.
+    pub fn empty() -> Self {
+        Self {
+            min: Point3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Point3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    /// Return the smallest bounding box that contains all of the points.
+    pub fn from_points(points: &[Point3<f32>]) -> Self {
+        points
+            .iter()
+            .fold(Self::empty(), |bounds, p| bounds.union_point(p))
+    }
+
+    /// Return the smallest bounding box that contains both bounding boxes.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: Point3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: Point3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+
+    /// Return the smallest bounding box that contains the bounding box and the
+    /// point.
+    pub fn union_point(&self, p: &Point3<f32>) -> Self {
+        Self {
+            min: Point3::new(self.min.x.min(p.x), self.min.y.min(p.y), self.min.z.min(p.z)),
+            max: Point3::new(self.max.x.max(p.x), self.max.y.max(p.y), self.max.z.max(p.z)),
+        }
+    }
+
+    /// Return the eight corners of the bounding box.
+    pub fn corners(&self) -> [Point3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Point3::new(min.x, min.y, min.z),
+            Point3::new(max.x, min.y, min.z),
+            Point3::new(min.x, max.y, min.z),
+            Point3::new(max.x, max.y, min.z),
+            Point3::new(min.x, min.y, max.z),
+            Point3::new(max.x, min.y, max.z),
+            Point3::new(min.x, max.y, max.z),
+            Point3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// Return a vector from `min` to `max`.
+    pub fn diagonal(&self) -> Vector3<f32> {
+        self.max - self.min
+    }
+
+    /// Return the point halfway between `min` and `max`.
+    pub fn centroid(&self) -> Point3<f32> {
+        self.min + 0.5 * self.diagonal()
+    }
+
+    /// Return the surface area of the box's six faces, or 0 if the box is
+    /// empty.
+    pub fn surface_area(&self) -> f32 {
+        let d = self.diagonal();
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            0.0
+        } else {
+            2.0 * (d.x * d.y + d.x * d.z + d.y * d.z)
+        }
+    }
+
+    /// Return the axis along which the box is longest.
+    pub fn maximum_extent(&self) -> Axis3 {
+        let d = self.diagonal();
+        if d.x > d.y && d.x > d.z {
+            Axis3::X
+        } else if d.y > d.z {
+            Axis3::Y
+        } else {
+            Axis3::Z
+        }
+    }
+
+    /// Return the position of a point relative to the corners of the box. A
+    /// point at `min` has an offset of (0, 0, 0), and a point at `max` has an
+    /// offset of (1, 1, 1).
+    pub fn offset(&self, p: &Point3<f32>) -> Vector3<f32> {
+        let mut o = p - self.min;
+        if self.max.x > self.min.x {
+            o.x /= self.max.x - self.min.x;
+        }
+        if self.max.y > self.min.y {
+            o.y /= self.max.y - self.min.y;
+        }
+        if self.max.z > self.min.z {
+            o.z /= self.max.z - self.min.z;
+        }
+        o
+    }
+
+    /// Return `true` if the ray passes through the box in the (0, `ray.t_max`)
+    /// parametric range.
+    ///
+    /// * inv_direction - The reciprocal of each component of the ray's
+    ///   direction.
+    /// * direction_is_negative - Indicates whether each component of the ray's
+    ///   direction is negative.
+    ///
+    /// The reciprocals and signs are passed in, rather than computed here, so
+    /// that they can be computed once per ray and reused for every box that
+    /// the ray is tested against.
+    pub fn intersect_p(
+        &self,
+        ray: &Ray,
+        inv_direction: &Vector3<f32>,
+        direction_is_negative: &[bool; 3],
+    ) -> bool {
+        let corner = |is_max: bool| if is_max { self.max } else { self.min };
+
+        // Find the parametric range in which the ray overlaps the x and y
+        // slabs. The far values are padded to account for rounding error so
+        // that rays that skim the box are not missed.
+        let mut t_min = (corner(direction_is_negative[0]).x - ray.origin.x) * inv_direction.x;
+        let mut t_max = (corner(!direction_is_negative[0]).x - ray.origin.x) * inv_direction.x;
+        let ty_min = (corner(direction_is_negative[1]).y - ray.origin.y) * inv_direction.y;
+        let ty_max = (corner(!direction_is_negative[1]).y - ray.origin.y) * inv_direction.y;
+        t_max *= 1.0 + 2.0 * efloat::gamma(3);
+        let ty_max = ty_max * (1.0 + 2.0 * efloat::gamma(3));
+        if t_min > ty_max || ty_min > t_max {
+            return false;
+        }
+        if ty_min > t_min {
+            t_min = ty_min;
+        }
+        if ty_max < t_max {
+            t_max = ty_max;
+        }
+
+        // Narrow the range down further using the z slab.
+        let tz_min = (corner(direction_is_negative[2]).z - ray.origin.z) * inv_direction.z;
+        let tz_max = (corner(!direction_is_negative[2]).z - ray.origin.z) * inv_direction.z;
+        let tz_max = tz_max * (1.0 + 2.0 * efloat::gamma(3));
+        if t_min > tz_max || tz_min > t_max {
+            return false;
+        }
+        if tz_min > t_min {
+            t_min = tz_min;
+        }
+        if tz_max < t_max {
+            t_max = tz_max;
+        }
+
+        t_min < ray.t_max && t_max > 0.0
+    }
+
+    /// Return `false` if none of a group of rays that share a direction can
+    /// pass through the box. This is conservative: if it returns `true`, some
+    /// or none of the rays might pass through the box, but if any ray would
+    /// pass `intersect_p`, this returns `true`.
+    ///
+    /// * origin_bounds - A bounding box around the rays' origins.
+    /// * t_max - The largest `t_max` of any of the rays.
+    /// * inv_direction - The reciprocal of each component of the rays'
+    ///   direction.
+    /// * direction_is_negative - Indicates whether each component of the rays'
+    ///   direction is negative.
+    pub fn intersect_p_parallel_rays(
+        &self,
+        origin_bounds: &Self,
+        t_max: f32,
+        inv_direction: &Vector3<f32>,
+        direction_is_negative: &[bool; 3],
+    ) -> bool {
+        let corner = |is_max: bool| if is_max { self.max } else { self.min };
+        let origin_corner = |is_max: bool| {
+            if is_max {
+                origin_bounds.max
+            } else {
+                origin_bounds.min
+            }
+        };
+
+        // Bound the slab ranges of all of the rays by pairing each side of the
+        // box with the farthest origin from it. `f32::max` and `f32::min`
+        // ignore NaNs, which arise for a ray that lies in a slab's plane, so
+        // such slabs don't narrow the range.
+        let mut range_min = f32::NEG_INFINITY;
+        let mut range_max = f32::INFINITY;
+        for axis in [Axis3::X, Axis3::Y, Axis3::Z] {
+            let i = axis as usize;
+            let inv = vector::component(*inv_direction, axis);
+            let near = point::component(corner(direction_is_negative[i]), axis)
+                - point::component(origin_corner(!direction_is_negative[i]), axis);
+            let far = point::component(corner(!direction_is_negative[i]), axis)
+                - point::component(origin_corner(direction_is_negative[i]), axis);
+            range_min = range_min.max(near * inv);
+            range_max = range_max.min(far * inv * (1.0 + 2.0 * efloat::gamma(3)));
+        }
+
+        range_min <= range_max && range_min < t_max && range_max > 0.0
+    }
+}
+
 #[cfg(test)]
 mod range_tests {
     use super::Bounds2;
@@ -75,3 +345,95 @@ mod range_tests {
         );
     }
 }
+
+#[cfg(test)]
+mod bounds3_tests {
+    use super::Bounds3;
+    use crate::ray::Ray;
+    use cgmath::{Point3, Vector3};
+
+    fn unit_cube() -> Bounds3<f32> {
+        Bounds3::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0))
+    }
+
+    fn intersect_p(bounds: &Bounds3<f32>, ray: &Ray) -> bool {
+        let inv_direction = ray.direction.map(|c| 1.0 / c);
+        let direction_is_negative = [
+            inv_direction.x < 0.0,
+            inv_direction.y < 0.0,
+            inv_direction.z < 0.0,
+        ];
+        bounds.intersect_p(ray, &inv_direction, &direction_is_negative)
+    }
+
+    #[test]
+    fn union_with_empty() {
+        let bounds = Bounds3::empty().union(&unit_cube());
+        assert_eq!(bounds, unit_cube());
+        assert_eq!(Bounds3::empty().surface_area(), 0.0);
+        assert_eq!(unit_cube().surface_area(), 6.0);
+    }
+
+    #[test]
+    fn ray_hits_and_misses() {
+        let bounds = unit_cube();
+        let hit = Ray::new(Point3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0), f32::MAX);
+        let miss = Ray::new(Point3::new(1.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0), f32::MAX);
+        let behind = Ray::new(Point3::new(0.5, 0.5, 2.0), Vector3::new(0.0, 0.0, 1.0), f32::MAX);
+        let too_short = Ray::new(Point3::new(0.5, 0.5, -1.0), Vector3::new(0.0, 0.0, 1.0), 0.5);
+        assert!(intersect_p(&bounds, &hit));
+        assert!(!intersect_p(&bounds, &miss));
+        assert!(!intersect_p(&bounds, &behind));
+        assert!(!intersect_p(&bounds, &too_short));
+    }
+
+    #[test]
+    fn parallel_rays_are_conservative() {
+        let bounds = unit_cube();
+        let directions = [
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.3, -0.2, 1.0),
+            Vector3::new(-1.0, 0.5, 0.0),
+        ];
+        for direction in directions {
+            let inv_direction = direction.map(|c| 1.0 / c);
+            let direction_is_negative = [
+                inv_direction.x < 0.0,
+                inv_direction.y < 0.0,
+                inv_direction.z < 0.0,
+            ];
+            // Slide a 3x3 grid of parallel rays across the box, so that some
+            // groups straddle its edges and some miss it entirely.
+            for offset in -20..20 {
+                let offset = offset as f32 * 0.1;
+                let rays: Vec<Ray> = (0..9)
+                    .map(|i| {
+                        let origin = Point3::new(
+                            offset + (i % 3) as f32 * 0.1,
+                            offset + (i / 3) as f32 * 0.1,
+                            -2.0,
+                        ) - direction;
+                        Ray::new(origin, direction, 4.0)
+                    })
+                    .collect();
+                let origins: Vec<Point3<f32>> = rays.iter().map(|r| r.origin).collect();
+                let any_hit = rays.iter().any(|r| intersect_p(&bounds, r));
+                let packet_hit = bounds.intersect_p_parallel_rays(
+                    &Bounds3::from_points(&origins),
+                    4.0,
+                    &inv_direction,
+                    &direction_is_negative,
+                );
+                assert!(packet_hit || !any_hit);
+            }
+        }
+
+        let far_away = [Point3::new(5.0, 5.0, -1.0), Point3::new(6.0, 6.0, -1.0)];
+        assert!(!bounds.intersect_p_parallel_rays(
+            &Bounds3::from_points(&far_away),
+            f32::MAX,
+            &Vector3::new(f32::INFINITY, f32::INFINITY, 1.0),
+            &[false; 3],
+        ));
+    }
+}
@@ -42,6 +42,7 @@ pub fn permute(v: Vector3<f32>, new_x: Axis3, new_y: Axis3, new_z: Axis3) -> Vec
 
 /// Returns two arbitrary vectors that are perpendicular to each other and the
 /// given vector.
+#[allow(clippy::neg_multiply)]
 pub fn arbitrary_coordinate_system(v: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
     let v2 = if v.x.abs() > v.y.abs() {
         Vector3::new(-1.0 * v.z, 0.0, v.x).normalize()
@@ -1,14 +1,22 @@
 use crate::{
     camera::Camera,
     color::RgbaSpectrum,
-    film::{Film, FilmTile},
-    filter::Filter,
-    geometry::bounds::Bounds2,
+    film::{Aov, Film, FilmTile, VisibleSurface},
+    filter::{Filter, FilterTable},
+    geometry::bounds::{Bounds2, Bounds3},
     ray::Ray,
     sampler::IncrementalSampler,
 };
 use cgmath::Point2;
-use rayon::prelude::*;
+use rayon::{prelude::*, ThreadPool};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
 
 pub trait RayTracer<Scene, Sampler: IncrementalSampler> {
     /// Determine the incoming radiance that arrives along the ray at the ray
@@ -32,6 +40,325 @@ pub trait RayTracer<Scene, Sampler: IncrementalSampler> {
         depth: usize,
         max_depth: usize,
     ) -> RgbaSpectrum;
+
+    /// Determine the incoming radiance that arrives along a primary ray at its
+    /// origin, along with the surface that the ray hits first.
+    ///
+    /// The default implementation doesn't find the visible surface, so the
+    /// film's auxiliary images stay empty.
+    ///
+    /// * `ray` - A primary ray from the camera.
+    /// * `scene` - The scene being rendered.
+    /// * `sampler` - The sampler that is used to solve the light transport
+    ///   equation using Monte Carlo integration.
+    /// * `max_depth` - The maximum number of ray bounces from the camera.
+    fn primary_sample(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        sampler: &mut Sampler,
+        max_depth: usize,
+    ) -> PrimarySample {
+        PrimarySample {
+            radiance: self.incoming_radiance(ray, scene, sampler, 0, max_depth),
+            visible_surface: None,
+            aovs: Vec::new(),
+        }
+    }
+
+    /// Return `true` if the primary rays of each tile should be traced
+    /// through the scene in packets with `primary_sample_packet`, rather than
+    /// one at a time with `primary_sample`.
+    ///
+    /// Primary rays are generated for every sample in a packet before any of
+    /// them are traced, so the sampler can't supply values for the rays of a
+    /// packet. Returning `true` promises that shading primary rays and any
+    /// secondary rays doesn't draw values from the sampler, and a ray tracer
+    /// that does must return `false`. So should a ray tracer whose scene would
+    /// trace the rays of a packet one at a time anyway.
+    fn traces_primary_ray_packets(&self, _scene: &Scene) -> bool {
+        false
+    }
+
+    /// Determine the incoming radiance that arrives along each of a packet of
+    /// primary rays at their origins, along with the surfaces that they hit
+    /// first. Secondary rays are traced one at a time with
+    /// `incoming_radiance`.
+    ///
+    /// This is only called if `traces_primary_ray_packets` returns `true`, so
+    /// implementations must not draw values from the sampler.
+    ///
+    /// * `rays` - Primary rays from the camera. These are often coherent, e.g.
+    ///   they share a direction if the camera is orthographic.
+    /// * `scene` - The scene being rendered.
+    /// * `sampler` - The sampler that generated the rays. It isn't positioned
+    ///   at the sample vector of each ray, so it's only passed on to methods
+    ///   that ignore it.
+    /// * `max_depth` - The maximum number of ray bounces from the camera.
+    fn primary_sample_packet(
+        &self,
+        rays: &[Ray],
+        scene: &Scene,
+        sampler: &mut Sampler,
+        max_depth: usize,
+    ) -> Vec<PrimarySample> {
+        rays.iter()
+            .map(|ray| self.primary_sample(ray, scene, sampler, max_depth))
+            .collect()
+    }
+}
+
+/// What a ray tracer finds along a primary ray.
+#[derive(Debug, Clone)]
+pub struct PrimarySample {
+    /// The incoming radiance that arrives along the ray at its origin.
+    pub radiance: RgbaSpectrum,
+
+    /// The surface that the ray hits first, or `None` if it doesn't hit
+    /// anything or the ray tracer doesn't report it. The film records it in
+    /// its standard channels.
+    pub visible_surface: Option<VisibleSurface>,
+
+    /// Values for any of the film's other channels, like the fraction of
+    /// lights that the visible surface is shadowed from.
+    pub aovs: Vec<Aov>,
+}
+
+impl PrimarySample {
+    /// Return the sample of a ray that doesn't hit anything.
+    pub fn transparent() -> Self {
+        Self {
+            radiance: RgbaSpectrum::transparent(),
+            visible_surface: None,
+            aovs: Vec::new(),
+        }
+    }
+}
+
+/// A scene that can report a bounding box around everything in it that a ray
+/// could hit.
+///
+/// `render` uses the bounding box to avoid tracing primary rays that can't hit
+/// anything, treating their radiance as transparent instead. A scene should
+/// only implement this trait if its ray tracers find no incoming radiance
+/// along rays that miss everything in the bounding box.
+pub trait BoundedScene {
+    /// Return a bounding box in world space around everything in the scene, or
+    /// `None` if the scene is empty.
+    fn world_bounds(&self) -> Option<Bounds3<f32>>;
+}
+
+/// The maximum number of primary rays that are traced together in a packet.
+const PACKET_SIZE: usize = 64;
+
+/// The order in which the tiles of an image are scheduled for rendering.
+///
+/// Each tile's samples are the same no matter when the tile is rendered, so the
+/// order only affects which parts of the image are finished first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TileOrder {
+    /// Row by row from the top of the image, and left to right within a row.
+    #[default]
+    RowMajor,
+
+    /// Spiraling outward from the tile at the center of the image, which is
+    /// usually where the subject of the image is.
+    Spiral,
+
+    /// Along a Hilbert curve, which keeps consecutive tiles next to each other
+    /// so that they tend to access the same parts of the scene.
+    Hilbert,
+}
+
+/// How much of an image has been rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// The number of tiles that have been rendered so far.
+    pub completed_tiles: usize,
+
+    /// The number of tiles in the image.
+    pub tile_count: usize,
+}
+
+impl Progress {
+    /// Return the fraction of the image's tiles that have been rendered, from
+    /// 0.0 to 1.0.
+    pub fn fraction(&self) -> f32 {
+        if self.tile_count == 0 {
+            1.0
+        } else {
+            self.completed_tiles as f32 / self.tile_count as f32
+        }
+    }
+}
+
+/// A function that is called with the progress of a render each time a tile
+/// is finished. It may be called from any of the threads rendering the image.
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// A function that is called with the number of finished passes and the film
+/// each time a pass over the image is finished, e.g. to show a snapshot of the
+/// image as it's refined.
+pub type PassCallback = Arc<dyn Fn(usize, &Film) + Send + Sync>;
+
+/// The error returned when a render is cancelled before it finishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rendering was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Measurements of a finished render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderSummary {
+    /// The number of complete passes over the image that were added to the
+    /// film.
+    pub passes: usize,
+
+    /// The number of samples whose primary rays were traced through the scene
+    /// in packets, rather than one at a time.
+    pub packet_samples: u64,
+}
+
+/// Options that control how the work of rendering an image is divided up and
+/// scheduled.
+#[derive(Clone)]
+pub struct RenderOptions {
+    /// The width and height of the tiles that are rendered in parallel. A tile
+    /// size of zero is treated as one.
+    pub tile_size: usize,
+
+    /// The order in which tiles are scheduled for rendering.
+    pub tile_order: TileOrder,
+
+    /// The thread pool in which tiles are rendered, or `None` to render them in
+    /// rayon's global thread pool.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+
+    /// The number of passes over the image. Every pass takes the sampler's
+    /// samples in each pixel, with different random numbers in each pass, and
+    /// adds them to the film, so every pass refines the image. Zero passes is
+    /// treated as one.
+    pub passes: usize,
+
+    /// The number of sample vectors that each pass takes in each pixel, or
+    /// `None` to take all of the sampler's samples in every pass. When a pass
+    /// takes fewer, consecutive passes take consecutive sample vectors from
+    /// the same round of the sampler's samples, so the samples in each pixel
+    /// are stratified across passes just as they are within a single pass.
+    /// Passes after the end of a round take samples from the next round.
+    /// Adaptive samplers always take all of their samples in every pass, since
+    /// they decide how many to take from the samples already in the pixel.
+    pub samples_per_pass: Option<usize>,
+
+    /// Called after each pass has been added to the film.
+    pub on_pass: Option<PassCallback>,
+
+    /// Called each time a tile is finished. The tile count includes the tiles
+    /// of every pass. If the render stops early at the deadline, it's called
+    /// once more with the tile count reduced to the number of tiles that were
+    /// rendered, so that the progress always ends at 100%.
+    pub progress: Option<ProgressCallback>,
+
+    /// The time by which the render should finish. The first pass over the
+    /// image is always finished so that the film holds a complete image, but
+    /// later passes are only started if they're expected to finish before the
+    /// deadline. Tiles of later passes aren't started after the deadline, so a
+    /// pass that's still running at the deadline adds only the tiles that were
+    /// finished to the film.
+    pub deadline: Option<Instant>,
+
+    /// A flag that cancels the render when it's set. It is checked before each
+    /// tile is rendered, so tiles that have already started are finished
+    /// first.
+    pub cancellation_token: Option<Arc<AtomicBool>>,
+}
+
+impl RenderOptions {
+    /// Run `op` in the thread pool that tiles should be rendered in, so that
+    /// any parallel work that it does, like building an acceleration
+    /// structure, is done there too.
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(thread_pool) => thread_pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Return `true` if the render has been cancelled.
+    fn is_cancelled(&self) -> bool {
+        self.cancellation_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    }
+
+    /// Return `true` if the deadline has passed.
+    fn is_past_deadline(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// The reasons that a pass over the image can stop before every tile has been
+/// rendered.
+enum Interruption {
+    Cancelled,
+    DeadlinePassed,
+}
+
+impl fmt::Debug for RenderOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RenderOptions")
+            .field("tile_size", &self.tile_size)
+            .field("tile_order", &self.tile_order)
+            .field("thread_pool", &self.thread_pool)
+            .field("passes", &self.passes)
+            .field("samples_per_pass", &self.samples_per_pass)
+            .field(
+                "on_pass",
+                &self.on_pass.as_ref().map(|_| "Fn(usize, &Film)"),
+            )
+            .field("progress", &self.progress.as_ref().map(|_| "Fn(Progress)"))
+            .field("deadline", &self.deadline)
+            .field("cancellation_token", &self.cancellation_token)
+            .finish()
+    }
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            tile_size: 16,
+            tile_order: TileOrder::default(),
+            thread_pool: None,
+            passes: 1,
+            samples_per_pass: None,
+            on_pass: None,
+            progress: None,
+            deadline: None,
+            cancellation_token: None,
+        }
+    }
+}
+
+/// The sample vectors that a tile takes in each of its pixels in one pass over
+/// the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TileSamples {
+    /// A seed for the tile's sampler.
+    pub seed: u64,
+
+    /// The index of the first sample vector taken in each pixel.
+    pub first_sample: usize,
+
+    /// The number of sample vectors taken in each pixel, or `None` to take
+    /// the rest of the round of samples that `first_sample` is in.
+    pub sample_count: Option<usize>,
 }
 
 /// * S - The type of sampler that is responsible for (1) choosing points on the image from
@@ -41,7 +368,15 @@ pub trait RayTracer<Scene, Sampler: IncrementalSampler> {
 /// * camera - Controls how the scene is viewed and contains the `Film` onto
 ///   which the scene is rendered.
 /// * filter -
-pub fn render<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
+/// * options - Controls how tiles of the image are scheduled, and reports
+///   progress.
+///
+/// Returns a summary of the render, whose number of passes is fewer than the
+/// number of passes in `options` if the deadline passed first. Returns
+/// `Err(Cancelled)` if the render is cancelled through `options`. The passes
+/// that were finished before then remain in the film.
+#[allow(clippy::too_many_arguments)]
+pub fn render<Scene: BoundedScene + Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
     scene: &Scene,
     camera: &(dyn Camera + Send + Sync),
     film: &mut Film,
@@ -49,30 +384,236 @@ pub fn render<Scene: Send + Sync, Sampler: IncrementalSampler + Send + Sync>(
     sampler: &Sampler,
     ray_tracer: &(dyn RayTracer<Scene, Sampler> + Send + Sync),
     max_depth: usize,
-) {
+    options: &RenderOptions,
+) -> Result<RenderSummary, Cancelled> {
     let image_sample_bounds = film.sample_bounds(filter.half_width(), filter.half_height());
+    let filter_table = FilterTable::new(filter);
+
+    // Samples outside of these raster space bounds can't see anything in the
+    // scene. If the camera can't bound what it sees, every sample is traced.
+    let visible_bounds = match scene.world_bounds() {
+        Some(world_bounds) => camera.raster_bounds(&world_bounds),
+        None => Some(Bounds2::empty()),
+    };
 
-    let film_tiles: Vec<FilmTile> = Tile::span_image_sample_bounds(&image_sample_bounds)
-        .par_iter()
-        .filter_map(|tile| {
-            // If the sampler generates random numbers, we don't want samplers in
-            // different tiles generating duplicate sequences of random numbers, so we
-            // use the tile's row-major index as a unique seed.
-            let mut sampler = sampler.clone_with_seed(tile.row_major_index as u64);
+    let tiles =
+        Tile::span_image_sample_bounds(&image_sample_bounds, options.tile_size, options.tile_order);
+    let samples_per_pixel = (!sampler.is_adaptive()).then(|| sampler.samples_per_pixel());
+    let packet_samples = AtomicU64::new(0);
+    let passes = render_passes(
+        film,
+        &tiles,
+        options,
+        samples_per_pixel,
+        |film, tile, tile_samples| {
+            let mut sampler = sampler.clone_with_seed(tile_samples.seed);
             render_tile::<Scene, Sampler>(
                 camera,
                 film,
                 scene,
                 tile,
-                filter,
+                tile_samples,
+                visible_bounds.as_ref(),
+                &filter_table,
                 &mut sampler,
                 ray_tracer,
                 max_depth,
+                &packet_samples,
             )
-        })
-        .collect();
+        },
+    )?;
+    Ok(RenderSummary {
+        passes,
+        packet_samples: packet_samples.into_inner(),
+    })
+}
+
+/// Render every pass over the image given by `options`, merging each pass into
+/// the film and reporting it through `options` once it's finished.
+///
+/// Each pass renders each of the tiles in parallel with `render_tile`, in the
+/// thread pool and order given by `options`. `render_tile` is given the film,
+/// the tile, and the samples that the tile takes in the pass. When `options`
+/// splits the sampler's samples across passes, `samples_per_pixel` is the
+/// number of sample vectors in each round of the sampler's samples, or `None`
+/// if they can't be split, as with adaptive samplers.
+///
+/// Progress is reported through `options` after each tile is rendered, and
+/// `Err(Cancelled)` is returned if the render is cancelled before every tile
+/// of every pass has started. Passes that were finished before then remain in
+/// the film. Otherwise, the number of complete passes that were added to the
+/// film is returned. If the deadline in `options` passes, the tiles of the
+/// current pass that were finished by then are also added to the film.
+pub(crate) fn render_passes(
+    film: &mut Film,
+    tiles: &[Tile],
+    options: &RenderOptions,
+    samples_per_pixel: Option<usize>,
+    render_tile: impl Fn(&Film, &Tile, TileSamples) -> Option<FilmTile> + Sync,
+) -> Result<usize, Cancelled> {
+    let passes = options.passes.max(1);
+    let completed_tiles = AtomicUsize::new(0);
+    let start = Instant::now();
+    for pass in 0..passes {
+        if pass > 0 {
+            // Don't start a pass that probably can't finish before the
+            // deadline, assuming that it takes as long as the average pass so
+            // far.
+            let average_pass_time = start.elapsed() / pass as u32;
+            let expected_end = Instant::now().checked_add(average_pass_time);
+            if let (Some(deadline), Some(expected_end)) = (options.deadline, expected_end) {
+                if expected_end > deadline {
+                    report_final_progress(options, &completed_tiles);
+                    return Ok(pass);
+                }
+            }
+        }
+
+        let (film_tiles, interruption) = render_tiles(
+            film,
+            tiles,
+            pass,
+            passes,
+            options,
+            samples_per_pixel,
+            &completed_tiles,
+            &render_tile,
+        );
+        match interruption {
+            Some(Interruption::Cancelled) => return Err(Cancelled),
+            Some(Interruption::DeadlinePassed) => {
+                // Keep the tiles that were finished in time. The first pass
+                // is never interrupted, so every pixel already has samples.
+                merge_tiles(film, film_tiles);
+                report_final_progress(options, &completed_tiles);
+                return Ok(pass);
+            }
+            None => merge_tiles(film, film_tiles),
+        }
+
+        if let Some(on_pass) = &options.on_pass {
+            on_pass(pass + 1, film);
+        }
+    }
+    Ok(passes)
+}
+
+/// Report the progress of a render that stopped early at the deadline, with
+/// the tile count reduced to the number of tiles that were rendered.
+fn report_final_progress(options: &RenderOptions, completed_tiles: &AtomicUsize) {
+    if let Some(progress) = &options.progress {
+        let completed_tiles = completed_tiles.load(Ordering::Relaxed);
+        progress(Progress {
+            completed_tiles,
+            tile_count: completed_tiles,
+        });
+    }
+}
+
+/// Render each of the tiles in parallel for one pass over the image. Return
+/// the film tiles paired with their tiles' row-major indices, along with the
+/// reason that the pass was interrupted, if it was. An interrupted pass is
+/// missing the film tiles of the tiles that hadn't started by then.
+#[allow(clippy::too_many_arguments)]
+fn render_tiles(
+    film: &Film,
+    tiles: &[Tile],
+    pass: usize,
+    passes: usize,
+    options: &RenderOptions,
+    samples_per_pixel: Option<usize>,
+    completed_tiles: &AtomicUsize,
+    render_tile: &(impl Fn(&Film, &Tile, TileSamples) -> Option<FilmTile> + Sync),
+) -> (Vec<(usize, FilmTile)>, Option<Interruption>) {
+    // Find the round of the sampler's samples that the pass takes samples
+    // from, and where in the round its samples start.
+    let (round, first_sample, sample_count) = match (options.samples_per_pass, samples_per_pixel) {
+        (Some(samples_per_pass), Some(samples_per_pixel))
+            if samples_per_pass < samples_per_pixel =>
+        {
+            let samples_per_pass = samples_per_pass.max(1);
+            let passes_per_round = samples_per_pixel.div_ceil(samples_per_pass);
+            (
+                pass / passes_per_round,
+                pass % passes_per_round * samples_per_pass,
+                Some(samples_per_pass),
+            )
+        }
+        _ => (pass, 0, None),
+    };
+
+    // Only passes after the first are interrupted by the deadline, so that the
+    // film always holds at least one complete pass.
+    let is_interrupted = || {
+        if options.is_cancelled() {
+            Some(Interruption::Cancelled)
+        } else if pass > 0 && options.is_past_deadline() {
+            Some(Interruption::DeadlinePassed)
+        } else {
+            None
+        }
+    };
+
+    let render_all = || {
+        tiles
+            .par_iter()
+            .map(|tile| {
+                if let Some(interruption) = is_interrupted() {
+                    return Err(interruption);
+                }
+
+                // If the sampler generates random numbers, we don't want samplers in
+                // different tiles or rounds generating duplicate sequences of random
+                // numbers, so we number the tiles of every round in row-major order and
+                // use the tile's number as a unique seed. Passes that take samples from
+                // the same round share seeds, so that they take different samples from
+                // the same sequence.
+                let seed = round * tiles.len() + tile.row_major_index;
+                let tile_samples = TileSamples {
+                    seed: seed as u64,
+                    first_sample,
+                    sample_count,
+                };
+                let film_tile = render_tile(film, tile, tile_samples);
+
+                if let Some(progress) = &options.progress {
+                    progress(Progress {
+                        completed_tiles: completed_tiles.fetch_add(1, Ordering::Relaxed) + 1,
+                        tile_count: passes * tiles.len(),
+                    });
+                }
+                Ok(film_tile.map(|film_tile| (tile.row_major_index, film_tile)))
+            })
+            .collect::<Vec<_>>()
+    };
+    let results = options.install(render_all);
 
-    for ft in film_tiles {
+    let mut film_tiles = Vec::with_capacity(results.len());
+    let mut interruption = None;
+    for result in results {
+        match result {
+            Ok(film_tile) => film_tiles.extend(film_tile),
+            Err(pass_interruption) => interruption = Some(pass_interruption),
+        }
+    }
+    // Tiles that were being rendered when the render was cancelled may have
+    // finished without an error, and cancellation takes precedence over the
+    // deadline.
+    if options.is_cancelled() {
+        interruption = Some(Interruption::Cancelled);
+    }
+    (film_tiles, interruption)
+}
+
+/// Merge rendered film tiles, paired with their tiles' row-major indices, into
+/// the film.
+///
+/// Tiles are merged in row-major order no matter which order they were
+/// rendered in, so that pixels that overlap several tiles sum the tiles'
+/// contributions in the same order every time.
+pub(crate) fn merge_tiles(film: &mut Film, mut film_tiles: Vec<(usize, FilmTile)>) {
+    film_tiles.sort_by_key(|(row_major_index, _)| *row_major_index);
+    for (_, ft) in film_tiles {
         film.merge_tile(&ft);
     }
 }
@@ -83,36 +624,82 @@ fn render_tile<Scene, Sampler: IncrementalSampler>(
     film: &Film,
     scene: &Scene,
     tile: &Tile,
-    filter: &dyn Filter,
+    tile_samples: TileSamples,
+    visible_bounds: Option<&Bounds2<f32>>,
+    filter: &FilterTable,
     sampler: &mut Sampler,
     ray_tracer: &dyn RayTracer<Scene, Sampler>,
     max_depth: usize,
+    packet_samples: &AtomicU64,
 ) -> Option<FilmTile> {
     let sample_bounds = tile.sample_bounds;
 
+    if let Some(visible_bounds) = visible_bounds {
+        // A sample contributes to pixels within the filter's radius of it, and
+        // those pixels only receive radiance from visible samples within the
+        // filter's radius of them. Tiles farther than that from the visible
+        // bounds only contribute to transparent pixels, so they're skipped.
+        // Pixel bounds are rounded outward, so each radius is padded by a
+        // pixel.
+        let reach_x = 2.0 * (filter.half_width() + 1.0);
+        let reach_y = 2.0 * (filter.half_height() + 1.0);
+        if !Bounds2::<f32>::from(sample_bounds).overlaps(&visible_bounds.padded(reach_x, reach_y)) {
+            return None;
+        }
+    }
+
     if let Some(mut film_tile) =
         film.tile(&sample_bounds, filter.half_width(), filter.half_height())
     {
+        // Adaptive samplers need the radiance of each sample before they can
+        // decide whether to take another, so their samples can't be traced in
+        // packets.
+        if ray_tracer.traces_primary_ray_packets(scene) && !sampler.is_adaptive() {
+            let tile_packet_samples = render_tile_in_packets(
+                camera,
+                scene,
+                &sample_bounds,
+                tile_samples,
+                visible_bounds,
+                &mut film_tile,
+                filter,
+                sampler,
+                ray_tracer,
+                max_depth,
+            );
+            packet_samples.fetch_add(tile_packet_samples, Ordering::Relaxed);
+            return Some(film_tile);
+        }
+
         for pixel_min_corner in sample_bounds.range() {
-            // let mut sample_count = 0;
-            sampler.start_pixel(pixel_min_corner);
+            let mut sample_count = 0;
+            sampler.start_pixel_sample(pixel_min_corner, tile_samples.first_sample);
             loop {
                 let sample = sampler.get_camera_sample(pixel_min_corner);
                 let (ray, _differential, weight) = camera.generate_ray_differential(&sample);
                 // TODO: Scale differential.
 
-                let radiance = if weight > 0.0 {
-                    ray_tracer.incoming_radiance(&ray, scene, sampler, 0, max_depth)
-                } else {
-                    RgbaSpectrum::transparent()
-                };
-
-                // TODO: Check for NaN or Inf values in spectrum.
+                let primary_sample =
+                    if weight > 0.0 && is_visible(visible_bounds, &sample.film_point) {
+                        ray_tracer.primary_sample(&ray, scene, sampler, max_depth)
+                    } else {
+                        PrimarySample::transparent()
+                    };
 
-                film_tile.add_sample(&sample.film_point, &radiance, weight, filter);
+                // Non-finite radiance is dropped by the film tile, and
+                // adaptive samplers leave it out of their statistics.
+                sampler.add_sample_radiance(&primary_sample.radiance);
+                film_tile.add_sample(
+                    &sample.film_point,
+                    &primary_sample.radiance,
+                    weight,
+                    filter,
+                    primary_sample.visible_surface.as_ref(),
+                    &primary_sample.aovs,
+                );
 
-                // sample_count += 1;
-                if !sampler.start_next_sample() {
+                sample_count += 1;
+                if tile_samples.sample_count == Some(sample_count) || !sampler.start_next_sample() {
                     break;
                 }
             }
@@ -123,55 +710,627 @@ fn render_tile<Scene, Sampler: IncrementalSampler>(
     }
 }
 
+/// Render the samples in the tile's sample bounds by tracing their primary
+/// rays in packets of up to `PACKET_SIZE` rays. Samples are added to the film
+/// tile in the same order as they are when each ray is traced on its own.
+/// Returns the number of samples whose primary rays were traced.
+#[allow(clippy::too_many_arguments)]
+fn render_tile_in_packets<Scene, Sampler: IncrementalSampler>(
+    camera: &dyn Camera,
+    scene: &Scene,
+    sample_bounds: &Bounds2<i32>,
+    tile_samples: TileSamples,
+    visible_bounds: Option<&Bounds2<f32>>,
+    film_tile: &mut FilmTile,
+    filter: &FilterTable,
+    sampler: &mut Sampler,
+    ray_tracer: &dyn RayTracer<Scene, Sampler>,
+    max_depth: usize,
+) -> u64 {
+    let mut samples = Vec::with_capacity(PACKET_SIZE);
+    let mut rays = Vec::with_capacity(PACKET_SIZE);
+    let mut traced_samples = 0;
+    let mut trace_packet = |samples: &mut Vec<(Point2<f32>, bool, f32)>,
+                            rays: &mut Vec<Ray>,
+                            sampler: &mut Sampler| {
+        let mut primary_samples = ray_tracer
+            .primary_sample_packet(rays, scene, sampler, max_depth)
+            .into_iter();
+        traced_samples += rays.len() as u64;
+        for (film_point, is_traced, weight) in samples.drain(..) {
+            let primary_sample = if is_traced {
+                primary_samples.next().unwrap()
+            } else {
+                PrimarySample::transparent()
+            };
+            film_tile.add_sample(
+                &film_point,
+                &primary_sample.radiance,
+                weight,
+                filter,
+                primary_sample.visible_surface.as_ref(),
+                &primary_sample.aovs,
+            );
+        }
+        rays.clear();
+    };
+
+    for pixel_min_corner in sample_bounds.range() {
+        let mut sample_count = 0;
+        sampler.start_pixel_sample(pixel_min_corner, tile_samples.first_sample);
+        loop {
+            let sample = sampler.get_camera_sample(pixel_min_corner);
+            let (ray, _differential, weight) = camera.generate_ray_differential(&sample);
+            let is_traced = weight > 0.0 && is_visible(visible_bounds, &sample.film_point);
+            if is_traced {
+                rays.push(ray);
+            }
+            samples.push((sample.film_point, is_traced, weight));
+            if samples.len() == PACKET_SIZE {
+                trace_packet(&mut samples, &mut rays, sampler);
+            }
+
+            sample_count += 1;
+            if tile_samples.sample_count == Some(sample_count) || !sampler.start_next_sample() {
+                break;
+            }
+        }
+    }
+    if !samples.is_empty() {
+        trace_packet(&mut samples, &mut rays, sampler);
+    }
+    traced_samples
+}
+
+/// Return `true` if a sample at the film point could see anything in the
+/// scene, given the raster space bounds of what the camera can see.
+fn is_visible(visible_bounds: Option<&Bounds2<f32>>, film_point: &Point2<f32>) -> bool {
+    visible_bounds.is_none_or(|bounds| bounds.contains(film_point))
+}
+
 /// A tile in an image's sample bounds that can be rendered in parallel with
 /// other tiles.
-struct Tile {
-    sample_bounds: Bounds2<i32>,
+pub(crate) struct Tile {
+    pub sample_bounds: Bounds2<i32>,
 
     /// The index of the tile in a vector represeting a row-major grid of tiles.
     ///
     /// This index is mostly useful as a unique ID for each tile. We will be
     /// able to use this as a unique pseudo-random number generator seed for
     /// each tile.
-    row_major_index: usize,
+    pub row_major_index: usize,
 }
 
 impl Tile {
-    /// Return a vector of 16-by-16 tiles that span the given image sample
-    /// bounds.
-    pub fn span_image_sample_bounds(image_sample_bounds: &Bounds2<i32>) -> Vec<Tile> {
-        const TILE_SIZE: usize = 16;
-        let image_sample_extent = image_sample_bounds.diagonal();
-        let tile_count_x = (image_sample_extent.x as usize + TILE_SIZE - 1) / TILE_SIZE;
-        let tile_count_y = (image_sample_extent.y as usize + TILE_SIZE - 1) / TILE_SIZE;
+    /// Return a vector of square tiles that span the given image sample
+    /// bounds, in the order in which they should be rendered.
+    ///
+    /// * `image_sample_bounds` - The bounds of the samples taken for the image.
+    /// * `tile_size` - The width and height of each tile. Tiles on the bottom
+    ///   and right edges of the image may be smaller.
+    /// * `tile_order` - The order of the returned tiles.
+    pub fn span_image_sample_bounds(
+        image_sample_bounds: &Bounds2<i32>,
+        tile_size: usize,
+        tile_order: TileOrder,
+    ) -> Vec<Tile> {
+        let tile_size = tile_size.max(1);
+        let (tile_count_x, tile_count_y) = Self::tile_counts(image_sample_bounds, tile_size);
 
         let xs = 0..tile_count_x;
         let ys = 0..tile_count_y;
-        ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+        let mut grid_points: Vec<(usize, usize)> =
+            ys.flat_map(|y| xs.clone().map(move |x| (x, y))).collect();
+        match tile_order {
+            TileOrder::RowMajor => {}
+            TileOrder::Spiral => grid_points = spiral_order(tile_count_x, tile_count_y),
+            TileOrder::Hilbert => {
+                let curve_size = tile_count_x.max(tile_count_y).next_power_of_two();
+                grid_points.sort_by_key(|&(x, y)| hilbert_curve_index(curve_size, x, y));
+            }
+        }
+
+        grid_points
+            .into_iter()
             .map(|(x, y)| Tile {
-                sample_bounds: Self::tile_sample_bounds(image_sample_bounds, x, y),
+                sample_bounds: Self::tile_sample_bounds(image_sample_bounds, tile_size, x, y),
                 row_major_index: y * tile_count_x + x,
             })
             .collect()
     }
 
+    /// Return the number of columns and rows of tiles that span the given image
+    /// sample bounds.
+    pub fn tile_counts(image_sample_bounds: &Bounds2<i32>, tile_size: usize) -> (usize, usize) {
+        let image_sample_extent = image_sample_bounds.diagonal();
+        (
+            (image_sample_extent.x as usize).div_ceil(tile_size),
+            (image_sample_extent.y as usize).div_ceil(tile_size),
+        )
+    }
+
     fn tile_sample_bounds(
         image_sample_bounds: &Bounds2<i32>,
+        tile_size: usize,
         tile_x_index: usize,
         tile_y_index: usize,
     ) -> Bounds2<i32> {
-        const TILE_SIZE: usize = 16;
         let min = Point2::new(
-            image_sample_bounds.min.x + (tile_x_index * TILE_SIZE) as i32,
-            image_sample_bounds.min.y + (tile_y_index * TILE_SIZE) as i32,
+            image_sample_bounds.min.x + (tile_x_index * tile_size) as i32,
+            image_sample_bounds.min.y + (tile_y_index * tile_size) as i32,
         );
         let max = Point2::new(
             // Tiles on the bottom and right edges might extend beyond the image
             // sample bounds, so be sure to limit the tile sample bounds to the
             // image sample bounds.
-            (min.x + TILE_SIZE as i32).min(image_sample_bounds.max.x),
-            (min.y + TILE_SIZE as i32).min(image_sample_bounds.max.y),
+            (min.x + tile_size as i32).min(image_sample_bounds.max.x),
+            (min.y + tile_size as i32).min(image_sample_bounds.max.y),
         );
         Bounds2::new(min, max)
     }
 }
+
+/// Return every point in a grid with the given number of columns and rows,
+/// ordered along a square spiral that starts at the center of the grid and
+/// turns clockwise.
+fn spiral_order(count_x: usize, count_y: usize) -> Vec<(usize, usize)> {
+    let count = count_x * count_y;
+    let mut points = Vec::with_capacity(count);
+    if count == 0 {
+        return points;
+    }
+
+    let (mut x, mut y) = ((count_x / 2) as isize, (count_y / 2) as isize);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut leg_length = 1;
+    let mut direction_index = 0;
+    points.push((x as usize, y as usize));
+    while points.len() < count {
+        // The spiral's legs grow by one step after every second turn.
+        for _ in 0..2 {
+            let (dx, dy) = directions[direction_index];
+            for _ in 0..leg_length {
+                x += dx;
+                y += dy;
+                if (0..count_x as isize).contains(&x) && (0..count_y as isize).contains(&y) {
+                    points.push((x as usize, y as usize));
+                }
+            }
+            direction_index = (direction_index + 1) % directions.len();
+        }
+        leg_length += 1;
+    }
+    points
+}
+
+/// Return the distance along a Hilbert curve that fills a `curve_size` by
+/// `curve_size` grid to the point (x, y) in the grid. `curve_size` must be a
+/// power of two.
+fn hilbert_curve_index(curve_size: usize, mut x: usize, mut y: usize) -> usize {
+    let mut index = 0;
+    let mut s = curve_size / 2;
+    while s > 0 {
+        let rx = (x & s > 0) as usize;
+        let ry = (y & s > 0) as usize;
+        index += s * s * ((3 * rx) ^ ry);
+
+        // Rotate the quadrant so that the curve within it has the standard
+        // orientation.
+        if ry == 0 {
+            if rx == 1 {
+                x = curve_size - 1 - x;
+                y = curve_size - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tile_tests {
+    use super::{Tile, TileOrder};
+    use crate::geometry::bounds::Bounds2;
+    use cgmath::Point2;
+
+    fn row_major_indices(tiles: &[Tile]) -> Vec<usize> {
+        tiles.iter().map(|tile| tile.row_major_index).collect()
+    }
+
+    #[test]
+    fn every_order_spans_the_same_tiles() {
+        let image_sample_bounds = Bounds2::new(Point2::new(-2, -2), Point2::new(101, 45));
+        let row_major =
+            Tile::span_image_sample_bounds(&image_sample_bounds, 8, TileOrder::RowMajor);
+        assert_eq!(
+            row_major_indices(&row_major),
+            (0..13 * 6).collect::<Vec<_>>()
+        );
+
+        for order in [TileOrder::Spiral, TileOrder::Hilbert] {
+            let tiles = Tile::span_image_sample_bounds(&image_sample_bounds, 8, order);
+            let mut indices = row_major_indices(&tiles);
+            indices.sort_unstable();
+            assert_eq!(indices, row_major_indices(&row_major));
+            for tile in tiles {
+                assert!(row_major[tile.row_major_index].sample_bounds == tile.sample_bounds);
+            }
+        }
+    }
+
+    #[test]
+    fn spiral_starts_at_the_center() {
+        let image_sample_bounds = Bounds2::new(Point2::new(0, 0), Point2::new(5, 3));
+        let tiles = Tile::span_image_sample_bounds(&image_sample_bounds, 1, TileOrder::Spiral);
+        assert_eq!(
+            row_major_indices(&tiles),
+            vec![7, 8, 13, 12, 11, 6, 1, 2, 3, 4, 9, 14, 10, 5, 0]
+        );
+    }
+
+    #[test]
+    fn hilbert_curve_visits_neighbors() {
+        let image_sample_bounds = Bounds2::new(Point2::new(0, 0), Point2::new(64, 64));
+        let tiles = Tile::span_image_sample_bounds(&image_sample_bounds, 4, TileOrder::Hilbert);
+        for pair in tiles.windows(2) {
+            let a = pair[0].sample_bounds.min;
+            let b = pair[1].sample_bounds.min;
+            assert_eq!((a.x - b.x).abs() + (a.y - b.y).abs(), 4);
+        }
+    }
+}
+
+#[cfg(test)]
+mod render_tiles_tests {
+    use super::{render_passes, Cancelled, Progress, RenderOptions, Tile, TileOrder, TileSamples};
+    use crate::{
+        color::RgbaSpectrum,
+        film::Film,
+        filter::{BoxFilter, FilterTable},
+        geometry::bounds::Bounds2,
+    };
+    use cgmath::{Point2, Vector2};
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    };
+
+    fn tiles() -> Vec<Tile> {
+        let image_sample_bounds = Bounds2::new(Point2::new(0, 0), Point2::new(64, 32));
+        Tile::span_image_sample_bounds(&image_sample_bounds, 16, TileOrder::RowMajor)
+    }
+
+    #[test]
+    fn reports_progress_for_every_tile() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let options = RenderOptions {
+            progress: Some({
+                let reports = reports.clone();
+                Arc::new(move |progress| reports.lock().unwrap().push(progress))
+            }),
+            ..RenderOptions::default()
+        };
+
+        let tiles = tiles();
+        let mut film = Film::new(Vector2::new(64, 32));
+        assert_eq!(
+            render_passes(&mut film, &tiles, &options, None, |_, _, _| None),
+            Ok(1)
+        );
+
+        let mut reports = reports.lock().unwrap().clone();
+        reports.sort_by_key(|progress| progress.completed_tiles);
+        assert_eq!(reports.len(), tiles.len());
+        for (i, progress) in reports.iter().enumerate() {
+            assert_eq!(progress.completed_tiles, i + 1);
+            assert_eq!(progress.tile_count, tiles.len());
+        }
+        assert_eq!(reports.last().unwrap().fraction(), 1.0);
+    }
+
+    #[test]
+    fn stops_rendering_tiles_when_cancelled() {
+        let cancellation_token = Arc::new(AtomicBool::new(false));
+        let options = RenderOptions {
+            cancellation_token: Some(cancellation_token.clone()),
+            ..RenderOptions::default()
+        };
+
+        let mut film = Film::new(Vector2::new(64, 32));
+        let result = render_passes(&mut film, &tiles(), &options, None, |_, _, _| {
+            cancellation_token.store(true, Ordering::Relaxed);
+            None
+        });
+        assert_eq!(result, Err(Cancelled));
+    }
+
+    #[test]
+    fn seeds_every_tile_of_every_pass_differently() {
+        let seeds = Arc::new(Mutex::new(vec![]));
+        let passes = Arc::new(Mutex::new(vec![]));
+        let options = RenderOptions {
+            passes: 3,
+            on_pass: Some({
+                let passes = passes.clone();
+                Arc::new(move |pass, _| passes.lock().unwrap().push(pass))
+            }),
+            ..RenderOptions::default()
+        };
+
+        let tiles = tiles();
+        let mut film = Film::new(Vector2::new(64, 32));
+        let result = render_passes(&mut film, &tiles, &options, None, |_, _, tile_samples| {
+            seeds.lock().unwrap().push(tile_samples.seed);
+            None
+        });
+        assert_eq!(result, Ok(3));
+
+        let mut seeds = seeds.lock().unwrap().clone();
+        seeds.sort_unstable();
+        assert_eq!(seeds, (0..3 * tiles.len() as u64).collect::<Vec<_>>());
+        assert_eq!(*passes.lock().unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn splits_each_round_of_samples_across_passes() {
+        let options = RenderOptions {
+            passes: 6,
+            samples_per_pass: Some(2),
+            ..RenderOptions::default()
+        };
+
+        let tiles = tiles();
+        let tile_samples = Mutex::new(vec![]);
+        let mut film = Film::new(Vector2::new(64, 32));
+        let result = render_passes(&mut film, &tiles, &options, Some(4), |_, tile, samples| {
+            if tile.row_major_index == 1 {
+                tile_samples.lock().unwrap().push(samples);
+            }
+            None
+        });
+        assert_eq!(result, Ok(6));
+
+        // Each round of four samples is split into two passes, which share the
+        // round's seed.
+        let round_samples = |round: usize, first_sample| TileSamples {
+            seed: (round * tiles.len() + 1) as u64,
+            first_sample,
+            sample_count: Some(2),
+        };
+        assert_eq!(
+            tile_samples.into_inner().unwrap(),
+            vec![
+                round_samples(0, 0),
+                round_samples(0, 2),
+                round_samples(1, 0),
+                round_samples(1, 2),
+                round_samples(2, 0),
+                round_samples(2, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn finishes_the_first_pass_after_the_deadline() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let options = RenderOptions {
+            passes: 3,
+            deadline: Some(Instant::now()),
+            progress: Some({
+                let reports = reports.clone();
+                Arc::new(move |progress| reports.lock().unwrap().push(progress))
+            }),
+            ..RenderOptions::default()
+        };
+
+        let tiles = tiles();
+        let rendered_tiles = AtomicUsize::new(0);
+        let mut film = Film::new(Vector2::new(64, 32));
+        let result = render_passes(&mut film, &tiles, &options, None, |_, _, _| {
+            rendered_tiles.fetch_add(1, Ordering::Relaxed);
+            None
+        });
+        assert_eq!(result, Ok(1));
+        assert_eq!(rendered_tiles.into_inner(), tiles.len());
+        assert_eq!(
+            *reports.lock().unwrap().last().unwrap(),
+            Progress {
+                completed_tiles: tiles.len(),
+                tile_count: tiles.len()
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_the_tiles_finished_before_the_deadline() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let deadline = Instant::now() + Duration::from_millis(250);
+        let options = RenderOptions {
+            passes: 3,
+            deadline: Some(deadline),
+            progress: Some({
+                let reports = reports.clone();
+                Arc::new(move |progress| reports.lock().unwrap().push(progress))
+            }),
+            // Render the tiles one at a time, so that exactly the tiles of the
+            // second pass before the one that passes the deadline are
+            // rendered.
+            thread_pool: Some(Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(1)
+                    .build()
+                    .unwrap(),
+            )),
+            ..RenderOptions::default()
+        };
+
+        let tiles = tiles();
+        let deadline_tile = tiles.len() / 2;
+        let filter_table = FilterTable::new(&BoxFilter::new(0.5, 0.5));
+        let rendered_tiles = AtomicUsize::new(0);
+        let mut film = Film::new(Vector2::new(64, 32));
+        let result = render_passes(
+            &mut film,
+            &tiles,
+            &options,
+            None,
+            |film, tile, tile_samples| {
+                rendered_tiles.fetch_add(1, Ordering::Relaxed);
+                // Pretend that this tile took until after the deadline in the
+                // second pass, whose seeds follow those of the first pass.
+                if tile_samples.seed == (tiles.len() + deadline_tile) as u64 {
+                    while Instant::now() < deadline {
+                        std::thread::sleep(deadline - Instant::now());
+                    }
+                }
+                // Take one sample in the center of a different pixel of the
+                // tile in each pass.
+                let pass = tile_samples.seed as usize / tiles.len();
+                let mut film_tile = film.tile(&tile.sample_bounds, 0.5, 0.5)?;
+                let pixel = tile.sample_bounds.min;
+                film_tile.add_sample(
+                    &Point2::new(pixel.x as f32 + pass as f32 + 0.5, pixel.y as f32 + 0.5),
+                    &RgbaSpectrum::from_rgb(1.0, 1.0, 1.0),
+                    1.0,
+                    &filter_table,
+                    None,
+                    &[],
+                );
+                Some(film_tile)
+            },
+        );
+        assert_eq!(result, Ok(1));
+
+        // The tile that passed the deadline had already started, so it's kept.
+        let rendered_tiles = rendered_tiles.into_inner();
+        assert_eq!(rendered_tiles, tiles.len() + deadline_tile + 1);
+        let image = film.write_image();
+        assert_eq!(
+            image.pixels().filter(|pixel| pixel.0[3] > 0).count(),
+            rendered_tiles
+        );
+        let last_report = *reports.lock().unwrap().last().unwrap();
+        assert_eq!(
+            last_report,
+            Progress {
+                completed_tiles: rendered_tiles,
+                tile_count: rendered_tiles
+            }
+        );
+        assert_eq!(last_report.fraction(), 1.0);
+    }
+}
+
+#[cfg(test)]
+mod render_tile_tests {
+    use super::{merge_tiles, render_tile, BoundedScene, Tile, TileOrder, TileSamples};
+    use crate::{
+        camera::{Camera, OrthographicCamera},
+        color::RgbaSpectrum,
+        film::Film,
+        filter::{FilterTable, MitchellFilter},
+        light::Light,
+        sampler::{IncrementalSampler, StratifiedSampler},
+        simple::{Material, OriginalRayTracer, PrimitiveAggregate, Scene},
+    };
+    use cgmath::{Matrix4, Point3, Vector2, Vector3};
+    use mesh::MeshBuilder;
+    use std::sync::atomic::AtomicU64;
+
+    #[test]
+    fn culling_leaves_the_image_unchanged() {
+        // A tall, thin rectangle, whose long edges partially cover the pixels
+        // that they run through. Its right edge is just short of the tiles
+        // that start at x = 30, whose samples still reach the edge's pixels.
+        let positions = vec![
+            Point3::new(0.07, -0.8, 1.0),
+            Point3::new(0.204, -0.8, 1.0),
+            Point3::new(0.204, 0.8, 1.0),
+            Point3::new(0.07, 0.8, 1.0),
+        ];
+        let normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+        let indices = vec![(0, 1, 2), (0, 2, 3)];
+        let mesh = MeshBuilder::new(positions, normals, indices).build();
+        let material = Material::new(
+            RgbaSpectrum::from_rgb(0.9, 0.5, 0.2),
+            0.1,
+            0.7,
+            0.3,
+            20.0,
+            0.0,
+        );
+        let light = Light::point_light(
+            Point3::new(-2.0, 2.0, -2.0),
+            RgbaSpectrum::from_rgb(1.0, 1.0, 1.0),
+        );
+        let scene = Scene::new(PrimitiveAggregate::from_mesh(&mesh, material), vec![light]);
+
+        let resolution = Vector2::new(48, 48);
+        let camera = OrthographicCamera::new(
+            Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0)),
+            0.0,
+            10.0,
+            Vector2::new(2.0, 2.0),
+            resolution,
+        );
+        // The Mitchell filter's negative lobes reach pixels beyond those that
+        // the rectangle covers, so they'd be wrong if too much were culled.
+        let filter = FilterTable::new(&MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0));
+        let sampler = StratifiedSampler::new(2, 2, 5, 0, true);
+        let visible_bounds = camera
+            .raster_bounds(&scene.world_bounds().unwrap())
+            .unwrap();
+
+        let render_image = |visible_bounds| {
+            let mut film = Film::new(resolution);
+            let image_sample_bounds = film.sample_bounds(filter.half_width(), filter.half_height());
+            let tiles =
+                Tile::span_image_sample_bounds(&image_sample_bounds, 8, TileOrder::RowMajor);
+            let film_tiles = tiles
+                .iter()
+                .filter_map(|tile| {
+                    let seed = tile.row_major_index as u64;
+                    let tile_samples = TileSamples {
+                        seed,
+                        first_sample: 0,
+                        sample_count: None,
+                    };
+                    let film_tile = render_tile(
+                        &camera,
+                        &film,
+                        &scene,
+                        tile,
+                        tile_samples,
+                        visible_bounds,
+                        &filter,
+                        &mut sampler.clone_with_seed(seed),
+                        &OriginalRayTracer {},
+                        5,
+                        &AtomicU64::new(0),
+                    )?;
+                    Some((tile.row_major_index, film_tile))
+                })
+                .collect();
+            merge_tiles(&mut film, film_tiles);
+            film.write_hdr()
+        };
+        let culled = render_image(Some(&visible_bounds));
+        let traced = render_image(None);
+
+        // Some of the tiles are culled entirely, but every pixel, including the
+        // partially covered pixels on the rectangle's edges, is the same.
+        let image_bounds_size = (resolution.x * resolution.y) as f32;
+        assert!(visible_bounds.area() < image_bounds_size / 4.0);
+        for (culled, traced) in culled.pixels().zip(traced.pixels()) {
+            assert_eq!(culled, traced);
+        }
+        assert!(culled.pixels().any(|p| p.0[3] > 0.0 && p.0[3] < 1.0));
+
+        // The negative lobes make the pixels just outside the edges negative.
+        assert!(culled.pixels().any(|p| p.0[3] < 0.0));
+    }
+}
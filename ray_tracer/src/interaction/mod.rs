@@ -1,7 +1,3 @@
-mod offset;
-
-pub use offset::OffsetRayOrigin;
-
 use cgmath::{Point3, Vector3};
 
 #[derive(Debug, Clone, Copy)]
@@ -1,3 +1,4 @@
+mod bvh;
 pub mod camera;
 pub mod color;
 pub mod film;
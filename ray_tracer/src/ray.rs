@@ -1,6 +1,6 @@
 use cgmath::{Matrix4, Point3, Transform, Vector3};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: Point3<f32>,
     pub direction: Vector3<f32>,
@@ -26,15 +26,6 @@ impl Ray {
     }
 }
 
-impl From<&Ray> for bvh::ray::Ray {
-    fn from(val: &Ray) -> Self {
-        bvh::ray::Ray::new(
-            bvh::Point3::new(val.origin.x, val.origin.y, val.origin.z),
-            bvh::Vector3::new(val.direction.x, val.direction.y, val.direction.z),
-        )
-    }
-}
-
 impl crate::geometry::Transform<Ray> for Matrix4<f32> {
     fn transform(&self, ray: &Ray) -> Ray {
         Ray {
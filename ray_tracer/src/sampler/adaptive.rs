@@ -0,0 +1,170 @@
+use super::IncrementalSampler;
+use crate::{color::RgbaSpectrum, film::PixelStatistics};
+use cgmath::Point2;
+
+/// A sampler that takes more samples in pixels whose values are far from
+/// converging, such as pixels on silhouette edges and specular highlights,
+/// and stops early in flat regions and empty background.
+///
+/// Samples are taken in rounds. Each round takes all of the base sampler's
+/// samples for the pixel. After each round, the error in the pixel's mean
+/// luminance is estimated from the variance of the samples taken so far, and
+/// another round is only started if the estimated error is above a threshold.
+pub struct AdaptiveSampler<S> {
+    base: S,
+
+    /// The maximum number of rounds of samples taken in each pixel.
+    max_rounds: usize,
+
+    /// Pixels whose relative error is at or below this threshold are
+    /// considered converged.
+    error_threshold: f32,
+
+    current_pixel: Point2<i32>,
+    current_round: usize,
+
+    /// Statistics about the samples taken in the current pixel so far.
+    statistics: PixelStatistics,
+}
+
+impl<S: IncrementalSampler> AdaptiveSampler<S> {
+    /// Create a new adaptive sampler.
+    ///
+    /// * base - Generates the samples in each round. At least one round of
+    ///   samples is taken in every pixel.
+    /// * max_samples_per_pixel - The maximum number of samples taken in any
+    ///   pixel. This is rounded down to a multiple of the base sampler's
+    ///   samples per pixel.
+    /// * error_threshold - Sampling in a pixel stops once the standard error of
+    ///   the pixel's mean luminance, relative to the mean, is at or below this
+    ///   threshold.
+    pub fn new(base: S, max_samples_per_pixel: usize, error_threshold: f32) -> Self {
+        let max_rounds = (max_samples_per_pixel / base.samples_per_pixel().max(1)).max(1);
+        Self {
+            base,
+            max_rounds,
+            error_threshold,
+            current_pixel: Point2::new(0, 0),
+            current_round: 0,
+            statistics: PixelStatistics::default(),
+        }
+    }
+}
+
+impl<S: IncrementalSampler> IncrementalSampler for AdaptiveSampler<S> {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        Self {
+            base: self.base.clone_with_seed(seed),
+            max_rounds: self.max_rounds,
+            error_threshold: self.error_threshold,
+            current_pixel: Point2::new(0, 0),
+            current_round: 0,
+            statistics: PixelStatistics::default(),
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.base.samples_per_pixel() * self.max_rounds
+    }
+
+    fn is_adaptive(&self) -> bool {
+        self.max_rounds > 1
+    }
+
+    fn add_sample_radiance(&mut self, radiance: &RgbaSpectrum) {
+        self.statistics.add_sample(radiance);
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        self.start_pixel_sample(pixel, 0);
+    }
+
+    fn start_pixel_sample(&mut self, pixel: Point2<i32>, sample_index: usize) {
+        self.current_pixel = pixel;
+        self.current_round = sample_index / self.base.samples_per_pixel().max(1);
+        self.statistics = PixelStatistics::default();
+        self.base.start_pixel_sample(pixel, sample_index);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.base.get_1d()
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        self.base.get_2d()
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        if self.base.start_next_sample() {
+            return true;
+        }
+
+        self.current_round += 1;
+        if self.current_round < self.max_rounds
+            && self.statistics.relative_error() > self.error_threshold
+        {
+            // Starting the pixel at the next round's first sample generates a
+            // new round of samples for it.
+            let sample_index = self.current_round * self.base.samples_per_pixel();
+            self.base
+                .start_pixel_sample(self.current_pixel, sample_index);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod adaptive_sampler_tests {
+    use super::AdaptiveSampler;
+    use crate::{
+        color::RgbaSpectrum,
+        sampler::{IncrementalSampler, StratifiedSampler},
+    };
+    use cgmath::Point2;
+
+    /// Take samples in a pixel until the sampler stops, reporting the radiance
+    /// returned by `radiance` for each one. Return the number of samples.
+    fn sample_pixel(
+        sampler: &mut AdaptiveSampler<StratifiedSampler>,
+        radiance: impl Fn(usize) -> RgbaSpectrum,
+    ) -> usize {
+        sampler.start_pixel(Point2::new(3, 4));
+        let mut sample_count = 0;
+        loop {
+            sampler.get_camera_sample(Point2::new(3, 4));
+            sampler.add_sample_radiance(&radiance(sample_count));
+            sample_count += 1;
+            if !sampler.start_next_sample() {
+                break;
+            }
+        }
+        sample_count
+    }
+
+    #[test]
+    fn stops_after_one_round_in_flat_pixels() {
+        let base = StratifiedSampler::new(2, 2, 5, 0, true);
+        let mut sampler = AdaptiveSampler::new(base, 64, 0.01);
+        assert_eq!(sampler.samples_per_pixel(), 64);
+
+        let sample_count = sample_pixel(&mut sampler, |_| RgbaSpectrum::transparent());
+        assert_eq!(sample_count, 4);
+    }
+
+    #[test]
+    fn keeps_sampling_noisy_pixels() {
+        let base = StratifiedSampler::new(2, 2, 5, 0, true);
+        let mut sampler = AdaptiveSampler::new(base, 64, 0.01);
+
+        let sample_count = sample_pixel(&mut sampler, |i| {
+            if i % 2 == 0 {
+                RgbaSpectrum::black()
+            } else {
+                RgbaSpectrum::from_rgb(1.0, 1.0, 1.0)
+            }
+        });
+        assert_eq!(sample_count, 64);
+    }
+}
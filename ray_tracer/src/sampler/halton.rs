@@ -0,0 +1,359 @@
+use super::{IncrementalSampler, MAX_SAMPLE};
+use cgmath::Point2;
+use rand::{prelude::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// The primes used as the bases of the Halton sequence's dimensions.
+/// Dimensions past the last prime are sampled with random numbers.
+const PRIMES: [u64; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// The largest number of pixels in each direction that the first two
+/// dimensions of the Halton sequence are spread over. The sample vectors of
+/// pixels that are further apart than this are taken from the same indices of
+/// the sequence.
+const MAX_RESOLUTION: u64 = 128;
+
+/// A sampler that generates sample vectors from the Halton sequence, a
+/// low-discrepancy sequence whose nth dimension is the radical inverse of the
+/// sample index in the nth prime base.
+///
+/// The sequence is spread over the whole image rather than restarted in every
+/// pixel, as described in PBR ed. 3, section 7.4. The first two dimensions of
+/// a sample vector determine the pixel it's in, so each pixel takes the
+/// samples of the sequence whose first two dimensions fall inside the pixel.
+///
+/// The sequence is randomized with random digit permutations: in each
+/// dimension, every digit of the radical inverse is replaced using a random
+/// permutation of the digits of that dimension's base. Digits that select the
+/// pixel are left alone, so the first two dimensions still fall inside it.
+pub struct HaltonSampler {
+    samples_per_pixel: usize,
+    rng: ChaCha8Rng,
+
+    /// A random permutation of the digits of each prime base in `PRIMES`.
+    permutations: Vec<Vec<u16>>,
+
+    /// The number of pixels in each direction that the first two dimensions are
+    /// spread over, which are powers of 2 and 3, respectively.
+    base_scales: [u64; 2],
+
+    /// The exponents of `base_scales`.
+    base_exponents: [u32; 2],
+
+    /// The multiplicative inverse of each base scale, modulo the other base
+    /// scale, which is used to find the first sample index in a pixel.
+    multiplicative_inverses: [u64; 2],
+
+    /// The index of the first sample of the current pixel in the sequence.
+    first_sample_index: u64,
+
+    /// The offset, modulo the base scales, of the current pixel.
+    pixel_offset: [u64; 2],
+
+    current_sample_index: usize,
+    current_dimension: usize,
+}
+
+impl HaltonSampler {
+    /// Create a new Halton sampler.
+    ///
+    /// * samples_per_pixel - The number of sample vectors generated for each
+    ///   pixel.
+    /// * seed - Used to initialize the pseudo-random number generator that
+    ///   generates the digit permutations.
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let permutations = PRIMES
+            .iter()
+            .map(|&base| {
+                let mut permutation: Vec<u16> = (0..base as u16).collect();
+                permutation.shuffle(&mut rng);
+                permutation
+            })
+            .collect();
+
+        let mut base_scales = [1, 1];
+        let mut base_exponents = [0, 0];
+        for ((base_scale, base_exponent), base) in base_scales
+            .iter_mut()
+            .zip(base_exponents.iter_mut())
+            .zip(PRIMES)
+        {
+            while *base_scale < MAX_RESOLUTION {
+                *base_scale *= base;
+                *base_exponent += 1;
+            }
+        }
+        let multiplicative_inverses = [
+            multiplicative_inverse(base_scales[1], base_scales[0]),
+            multiplicative_inverse(base_scales[0], base_scales[1]),
+        ];
+
+        Self {
+            samples_per_pixel,
+            rng,
+            permutations,
+            base_scales,
+            base_exponents,
+            multiplicative_inverses,
+            first_sample_index: 0,
+            pixel_offset: [0, 0],
+            current_sample_index: 0,
+            current_dimension: 0,
+        }
+    }
+
+    /// The number of consecutive indices in the sequence that visit every pixel
+    /// in the area spanned by the base scales once.
+    fn sample_stride(&self) -> u64 {
+        self.base_scales[0] * self.base_scales[1]
+    }
+
+    /// Return the value of the given dimension of the current sample vector.
+    fn sample_dimension(&mut self, dimension: usize) -> f32 {
+        if dimension >= PRIMES.len() {
+            return self.rng.gen::<f32>().min(MAX_SAMPLE);
+        }
+
+        let index =
+            self.first_sample_index + self.current_sample_index as u64 * self.sample_stride();
+        if dimension < 2 {
+            // Scale the value to the pixels spanned by the base scale, and
+            // subtract the pixel's offset to leave the position in the pixel.
+            let first_scrambled_digit = self.base_exponents[dimension];
+            let value = scrambled_radical_inverse(
+                PRIMES[dimension],
+                &self.permutations[dimension],
+                index,
+                first_scrambled_digit,
+            );
+            let offset =
+                value * self.base_scales[dimension] as f64 - self.pixel_offset[dimension] as f64;
+            (offset as f32).clamp(0.0, MAX_SAMPLE)
+        } else {
+            let value = scrambled_radical_inverse(
+                PRIMES[dimension],
+                &self.permutations[dimension],
+                index,
+                0,
+            );
+            (value as f32).min(MAX_SAMPLE)
+        }
+    }
+}
+
+impl IncrementalSampler for HaltonSampler {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        Self::new(self.samples_per_pixel, seed)
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        self.start_pixel_sample(pixel, 0);
+    }
+
+    /// Start sampling work on a given pixel at the sample vector with the
+    /// given index. The pixel's sample vectors are consecutive samples of the
+    /// sequence in the pixel, so later rounds continue the sequence rather
+    /// than repeating the first round's samples.
+    fn start_pixel_sample(&mut self, pixel: Point2<i32>, sample_index: usize) {
+        self.pixel_offset = [
+            pixel.x.rem_euclid(self.base_scales[0] as i32) as u64,
+            pixel.y.rem_euclid(self.base_scales[1] as i32) as u64,
+        ];
+
+        // The first `base_exponents[0]` digits of an index's radical inverse in
+        // base 2 select the pixel's x offset, so the index's remainder modulo
+        // `base_scales[0]` is found by reversing those digits, and likewise for
+        // base 3 and the y offset. The Chinese remainder theorem combines the
+        // two remainders into the first index in the sequence with both.
+        let sample_stride = self.sample_stride();
+        let first_sample_index: u64 = (0..2)
+            .map(|dimension| {
+                let remainder = inverse_radical_inverse(
+                    PRIMES[dimension],
+                    self.pixel_offset[dimension],
+                    self.base_exponents[dimension],
+                );
+                remainder
+                    * (sample_stride / self.base_scales[dimension])
+                    * self.multiplicative_inverses[dimension]
+            })
+            .sum();
+        self.first_sample_index = first_sample_index % sample_stride;
+        self.current_sample_index = sample_index;
+        self.current_dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let value = self.sample_dimension(self.current_dimension);
+        self.current_dimension += 1;
+        value
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        let x = self.sample_dimension(self.current_dimension);
+        let y = self.sample_dimension(self.current_dimension + 1);
+        self.current_dimension += 2;
+        Point2::new(x, y)
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.current_sample_index += 1;
+        self.current_dimension = 0;
+        !self
+            .current_sample_index
+            .is_multiple_of(self.samples_per_pixel.max(1))
+    }
+}
+
+/// Return the radical inverse of `index` in the given base, which mirrors the
+/// digits of `index` about the radix point, after replacing each digit from
+/// `first_scrambled_digit` onwards with its entry in `permutation`.
+///
+/// Since the permutation may replace zero with another digit, the infinite
+/// trailing zeros of the radical inverse are replaced too, which adds a
+/// geometric series to the result.
+fn scrambled_radical_inverse(
+    base: u64,
+    permutation: &[u16],
+    mut index: u64,
+    first_scrambled_digit: u32,
+) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_n = 1.0;
+    let mut result = 0.0;
+    let mut digit_index = 0;
+    while index > 0 || digit_index < first_scrambled_digit {
+        let digit = index % base;
+        let digit = if digit_index >= first_scrambled_digit {
+            permutation[digit as usize] as u64
+        } else {
+            digit
+        };
+        inv_base_n *= inv_base;
+        result += digit as f64 * inv_base_n;
+        index /= base;
+        digit_index += 1;
+    }
+    result + permutation[0] as f64 * inv_base_n * inv_base / (1.0 - inv_base)
+}
+
+/// Return the index whose first `digit_count` digits of its radical inverse in
+/// the given base are the digits of `inverse`, reversed.
+fn inverse_radical_inverse(base: u64, mut inverse: u64, digit_count: u32) -> u64 {
+    let mut index = 0;
+    for _ in 0..digit_count {
+        let digit = inverse % base;
+        inverse /= base;
+        index = index * base + digit;
+    }
+    index
+}
+
+/// Return the multiplicative inverse of `a` modulo `n`, where `a` and `n` are
+/// relatively prime.
+fn multiplicative_inverse(a: u64, n: u64) -> u64 {
+    let (x, _) = extended_gcd(a as i64, n as i64);
+    x.rem_euclid(n as i64) as u64
+}
+
+/// Return `(x, y)` such that `a * x + b * y` is the greatest common divisor of
+/// `a` and `b`.
+fn extended_gcd(a: i64, b: i64) -> (i64, i64) {
+    if b == 0 {
+        return (1, 0);
+    }
+    let (x, y) = extended_gcd(b, a % b);
+    (y, x - (a / b) * y)
+}
+
+#[cfg(test)]
+mod halton_sampler_tests {
+    use super::super::{AdaptiveSampler, IncrementalSampler};
+    use super::{scrambled_radical_inverse, HaltonSampler};
+    use crate::color::RgbaSpectrum;
+    use cgmath::point2;
+
+    #[test]
+    fn radical_inverse_mirrors_digits() {
+        let identity = [0, 1, 2];
+        // 5 is 12 in base 3, so its radical inverse is 0.21 in base 3.
+        let value = scrambled_radical_inverse(3, &identity, 5, 0);
+        assert!((value - 7.0 / 9.0).abs() < 1e-9);
+
+        // Swapping 0 and 1 replaces the trailing zeros of 0.21 with ones.
+        let permutation = [1, 0, 2];
+        let value = scrambled_radical_inverse(3, &permutation, 5, 0);
+        assert!((value - (2.0 / 3.0 + 0.0 / 9.0 + 1.0 / 18.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn samples_are_inside_the_pixel_and_distinct() {
+        let mut sampler = HaltonSampler::new(16, 7);
+        for pixel in [point2(0, 0), point2(5, 3), point2(130, 250), point2(-1, -1)] {
+            sampler.start_pixel(pixel);
+            let mut film_samples = vec![];
+            loop {
+                let sample = sampler.get_camera_sample(pixel);
+                let offset = sample.film_point - pixel.cast::<f32>().unwrap();
+                assert!((0.0..1.0).contains(&offset.x) && (0.0..1.0).contains(&offset.y));
+                assert!((0.0..1.0).contains(&sample.time));
+                film_samples.push(offset);
+                if !sampler.start_next_sample() {
+                    break;
+                }
+            }
+            assert_eq!(film_samples.len(), 16);
+
+            // The sample vectors' x offsets are spread evenly over the pixel,
+            // one in each sixteenth.
+            let mut strata: Vec<usize> = film_samples
+                .iter()
+                .map(|offset| (offset.x * 16.0) as usize)
+                .collect();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..16).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn adaptive_rounds_continue_the_sequence() {
+        let pixel = point2(5, 3);
+        let mut sampler = AdaptiveSampler::new(HaltonSampler::new(4, 7), 16, 0.01);
+        sampler.start_pixel(pixel);
+        let mut film_samples = vec![];
+        loop {
+            let sample = sampler.get_camera_sample(pixel);
+            film_samples.push(sample.film_point - pixel.cast::<f32>().unwrap());
+            // Alternating radiance keeps the pixel from converging, so every
+            // round is taken.
+            let radiance = if film_samples.len() % 2 == 0 {
+                RgbaSpectrum::black()
+            } else {
+                RgbaSpectrum::from_rgb(1.0, 1.0, 1.0)
+            };
+            sampler.add_sample_radiance(&radiance);
+            if !sampler.start_next_sample() {
+                break;
+            }
+        }
+        assert_eq!(film_samples.len(), 16);
+
+        // The four rounds take 16 consecutive samples of the sequence, rather
+        // than the first four samples four times, so they're spread over the
+        // pixel as evenly as 16 samples in a single round are.
+        let mut strata: Vec<usize> = film_samples
+            .iter()
+            .map(|offset| (offset.x * 16.0) as usize)
+            .collect();
+        strata.sort_unstable();
+        assert_eq!(strata, (0..16).collect::<Vec<_>>());
+    }
+}
@@ -1,9 +1,15 @@
+mod adaptive;
+mod halton;
 mod pixel;
+mod sobol;
 mod stratified;
 
+pub use adaptive::AdaptiveSampler;
+pub use halton::HaltonSampler;
+pub use sobol::SobolSampler;
 pub use stratified::StratifiedSampler;
 
-use crate::camera::CameraSample;
+use crate::{camera::CameraSample, color::RgbaSpectrum};
 use cgmath::Point2;
 use core::f32;
 
@@ -34,9 +40,23 @@ pub trait IncrementalSampler {
     fn clone_with_seed(&self, seed: u64) -> Self;
 
     /// Return the number of n-dimensional sample vectors that will be generated
-    /// for each pixel in the image.
+    /// for each pixel in the image. Adaptive samplers return the maximum
+    /// number of sample vectors that they generate for any pixel.
     fn samples_per_pixel(&self) -> usize;
 
+    /// Return `true` if the number of sample vectors that the sampler
+    /// generates for a pixel depends on the radiance of the samples already
+    /// taken in the pixel. The radiance of each sample must then be reported
+    /// with `add_sample_radiance` before `start_next_sample` is called.
+    fn is_adaptive(&self) -> bool {
+        false
+    }
+
+    /// Report the radiance that was found for the current sample vector.
+    /// Adaptive samplers use it to decide whether to take more samples in the
+    /// current pixel, and other samplers ignore it.
+    fn add_sample_radiance(&mut self, _radiance: &RgbaSpectrum) {}
+
     /// Start sampling work on a given pixel. All subseqent requests to the
     /// sampler will generate samples for the given pixel, up until
     /// `start_pixel` is called again with a different pixel.
@@ -47,6 +67,27 @@ pub trait IncrementalSampler {
     ///   are equivalent.
     fn start_pixel(&mut self, pixel: Point2<i32>);
 
+    /// Start sampling work on a given pixel, as with `start_pixel`, but begin
+    /// with the sample vector at the given index, rather than the first one.
+    ///
+    /// The sample vectors of a pixel are numbered in the order in which
+    /// they're generated, and are taken in rounds of `samples_per_pixel`
+    /// vectors. `start_next_sample` returns `false` at the end of the round
+    /// that contains `sample_index`. Samplers that generate each round of a
+    /// pixel's samples at random when the pixel is started take a new round
+    /// every time, so starting a pixel at different indices only takes
+    /// different samples from the same round in samplers that are cloned with
+    /// the same seed and started on the same pixels in the same order.
+    ///
+    /// * pixel - A point identifying the pixel, as in `start_pixel`.
+    /// * sample_index - The index of the first sample vector to generate.
+    fn start_pixel_sample(&mut self, pixel: Point2<i32>, sample_index: usize) {
+        self.start_pixel(pixel);
+        for _ in 0..sample_index % self.samples_per_pixel().max(1) {
+            self.start_next_sample();
+        }
+    }
+
     /// Get a 1D value for the next dimension of the current sample vector.
     ///
     /// This method mutates the sampler by incrementing the current sample
@@ -0,0 +1,226 @@
+use super::{pixel::PixelSamplerState, IncrementalSampler, MAX_SAMPLE};
+use cgmath::{point2, Point2};
+use rand::{prelude::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// A sampler that generates the sample vectors of each pixel from the first
+/// two dimensions of the Sobol sequence, a low-discrepancy sequence whose
+/// first `2^m` points in those dimensions have exactly one point in every
+/// elementary interval of area `2^-m`.
+///
+/// Every pixel gets its own randomization of the sequence with Owen
+/// scrambling, which randomly flips each bit of a sample's value depending on
+/// the bits before it. This keeps the sequence's stratification while making
+/// each sample uniformly distributed. As with `StratifiedSampler`, the samples
+/// of each dimension are shuffled, so that dimensions of the same sample
+/// vector aren't correlated.
+pub struct SobolSampler {
+    samples_per_pixel: usize,
+    max_dimension_requests: usize,
+    pixel_sampler_state: PixelSamplerState,
+    rng: ChaCha8Rng,
+}
+
+impl SobolSampler {
+    /// Create a new Sobol sampler.
+    ///
+    /// * samples_per_pixel - The number of sample vectors generated for each
+    ///   pixel. This is rounded up to a power of two, since that's when the
+    ///   Sobol sequence is best distributed.
+    /// * max_dimension_requests - The maximum number of 1D and 2D requests
+    ///   that are made for each sample vector.
+    /// * seed - Used to initialize the pseudo-random number generator that
+    ///   scrambles and shuffles the samples.
+    pub fn new(samples_per_pixel: usize, max_dimension_requests: usize, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1).next_power_of_two();
+        Self {
+            samples_per_pixel,
+            max_dimension_requests,
+            pixel_sampler_state: PixelSamplerState::new(samples_per_pixel, max_dimension_requests),
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Generate a 1D sample for each sample vector of the pixel from the first
+    /// dimension of the Sobol sequence.
+    fn sobol_samples_1d(&mut self) -> Vec<f32> {
+        let scramble_seed = self.rng.gen();
+        (0..self.samples_per_pixel as u32)
+            .map(|index| to_sample(owen_scramble(sobol_2d(index).0, scramble_seed)))
+            .collect()
+    }
+
+    /// Generate a 2D sample for each sample vector of the pixel from the first
+    /// two dimensions of the Sobol sequence.
+    fn sobol_samples_2d(&mut self) -> Vec<Point2<f32>> {
+        let x_scramble_seed = self.rng.gen();
+        let y_scramble_seed = self.rng.gen();
+        (0..self.samples_per_pixel as u32)
+            .map(|index| {
+                let (x, y) = sobol_2d(index);
+                point2(
+                    to_sample(owen_scramble(x, x_scramble_seed)),
+                    to_sample(owen_scramble(y, y_scramble_seed)),
+                )
+            })
+            .collect()
+    }
+}
+
+impl IncrementalSampler for SobolSampler {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        Self::new(self.samples_per_pixel, self.max_dimension_requests, seed)
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel(&mut self, _pixel: Point2<i32>) {
+        let mut precomputed_1d: Vec<Vec<f32>> = (0..self.max_dimension_requests)
+            .map(|_| self.sobol_samples_1d())
+            .collect();
+        let mut precomputed_2d: Vec<Vec<Point2<f32>>> = (0..self.max_dimension_requests)
+            .map(|_| self.sobol_samples_2d())
+            .collect();
+
+        for dim in precomputed_1d.iter_mut() {
+            dim.shuffle(&mut self.rng);
+        }
+        for dim in precomputed_2d.iter_mut() {
+            dim.shuffle(&mut self.rng);
+        }
+
+        self.pixel_sampler_state
+            .start_pixel(precomputed_1d, precomputed_2d);
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        self.pixel_sampler_state.get_1d()
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        self.pixel_sampler_state.get_2d()
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        self.pixel_sampler_state.start_next_sample()
+    }
+}
+
+/// Return the first two dimensions of the `index`th point of the Sobol
+/// sequence, as fixed-point fractions with 32 bits after the radix point.
+///
+/// The first dimension is the van der Corput sequence, the radical inverse of
+/// the index in base 2. The second dimension is generated by the primitive
+/// polynomial `x + 1`, whose direction numbers are found by xor-ing each one
+/// with itself shifted right by one bit.
+fn sobol_2d(index: u32) -> (u32, u32) {
+    let x = index.reverse_bits();
+    let mut y = 0;
+    let mut direction = 1 << 31;
+    let mut index = index;
+    while index != 0 {
+        if index & 1 == 1 {
+            y ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+    (x, y)
+}
+
+/// Apply an Owen scramble to a fixed-point fraction, using the hash-based
+/// nested uniform scramble from Burley's "Practical Hash-based Owen
+/// Scrambling". Reversing the bits lets a hash in which each bit only depends
+/// on the lower bits flip each bit depending on the bits before it.
+fn owen_scramble(value: u32, seed: u32) -> u32 {
+    let mut x = value.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50_b47c);
+    x ^= x.wrapping_mul(0xb82f_1e52);
+    x ^= x.wrapping_mul(0xc7af_e638);
+    x ^= x.wrapping_mul(0x8d22_f6e6);
+    x.reverse_bits()
+}
+
+/// Convert a fixed-point fraction to a sample in [0, 1).
+fn to_sample(value: u32) -> f32 {
+    // Converting to `f32` can round up to 1.0, so we need to clamp to
+    // `MAX_SAMPLE`.
+    (value as f32 * (1.0 / 4_294_967_296.0)).min(MAX_SAMPLE)
+}
+
+#[cfg(test)]
+mod sobol_sampler_tests {
+    use super::super::IncrementalSampler;
+    use super::{owen_scramble, sobol_2d, to_sample, SobolSampler};
+    use cgmath::point2;
+
+    /// Check that 16 2D points have one point in each elementary interval of
+    /// area 1/16: each of the 1x16, 2x8, 4x4, 8x2 and 16x1 grids of cells.
+    fn assert_elementary_intervals(points: &[(f32, f32)]) {
+        assert_eq!(points.len(), 16);
+        for x_bits in 0..=4 {
+            let (x_cells, y_cells) = (1 << x_bits, 1 << (4 - x_bits));
+            let mut cells: Vec<usize> = points
+                .iter()
+                .map(|(x, y)| {
+                    (x * x_cells as f32) as usize * y_cells + (y * y_cells as f32) as usize
+                })
+                .collect();
+            cells.sort_unstable();
+            assert_eq!(
+                cells,
+                (0..16).collect::<Vec<_>>(),
+                "{}x{}",
+                x_cells,
+                y_cells
+            );
+        }
+    }
+
+    #[test]
+    fn sequence_fills_elementary_intervals() {
+        let points: Vec<(f32, f32)> = (0..16)
+            .map(|index| {
+                let (x, y) = sobol_2d(index);
+                (to_sample(x), to_sample(y))
+            })
+            .collect();
+        assert_eq!(
+            &points[..4],
+            &[(0.0, 0.0), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25)]
+        );
+        assert_elementary_intervals(&points);
+
+        let scrambled: Vec<(f32, f32)> = (0..16)
+            .map(|index| {
+                let (x, y) = sobol_2d(index);
+                (
+                    to_sample(owen_scramble(x, 0x1234_5678)),
+                    to_sample(owen_scramble(y, 0x9abc_def0)),
+                )
+            })
+            .collect();
+        assert_ne!(scrambled, points);
+        assert_elementary_intervals(&scrambled);
+    }
+
+    #[test]
+    fn rounds_samples_up_to_a_power_of_two() {
+        let mut sampler = SobolSampler::new(12, 5, 0);
+        assert_eq!(sampler.samples_per_pixel(), 16);
+
+        sampler.start_pixel(point2(3, 4));
+        let mut film_samples = vec![];
+        loop {
+            let sample = sampler.get_camera_sample(point2(0, 0));
+            film_samples.push((sample.film_point.x, sample.film_point.y));
+            if !sampler.start_next_sample() {
+                break;
+            }
+        }
+        assert_elementary_intervals(&film_samples);
+    }
+}
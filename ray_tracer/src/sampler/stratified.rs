@@ -296,6 +296,30 @@ mod stratified_sampler_tests {
         }
     }
 
+    #[test]
+    fn starts_pixels_at_a_sample_index() {
+        let sampler = StratifiedSampler::new(2, 3, 5, 0, true);
+        let mut samples = vec![];
+        let mut all_samples = sampler.clone_with_seed(1);
+        all_samples.start_pixel(point2(3, 4));
+        loop {
+            samples.push(all_samples.get_2d());
+            if !all_samples.start_next_sample() {
+                break;
+            }
+        }
+
+        // Samplers with the same seed take the same round of samples, so each
+        // index starts at the same sample as it does when the round is taken
+        // from the start.
+        for (index, sample) in samples.iter().enumerate() {
+            let mut sampler = sampler.clone_with_seed(1);
+            sampler.start_pixel_sample(point2(3, 4), index);
+            assert_eq!(sampler.get_2d(), *sample);
+            assert_eq!(sampler.start_next_sample(), index + 1 < samples.len());
+        }
+    }
+
     /// Check if the sample is in the strata defined by the given min and max
     /// bounds. Bounds are inclusive.
     fn in_strata_1d(sample: &f32, min_max: &(f32, f32)) -> bool {
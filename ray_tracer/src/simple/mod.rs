@@ -1,9 +1,11 @@
 mod material;
 mod primitive;
+mod rasterizer;
 mod ray_tracer;
 mod scene;
 
 pub use material::Material;
 pub use primitive::{Primitive, PrimitiveAggregate};
+pub use rasterizer::rasterize;
 pub use ray_tracer::OriginalRayTracer;
 pub use scene::Scene;
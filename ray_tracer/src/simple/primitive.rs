@@ -1,10 +1,11 @@
 use super::material::Material;
+use crate::bvh::{Bounded, Bvh};
+use crate::film::VisibleSurface;
+use crate::geometry::bounds::Bounds3;
 use crate::interaction::SurfaceInteraction;
 use crate::number;
 use crate::ray::Ray;
-use crate::triangle::Triangle;
-use bvh::bvh::BVH;
-use bvh::{aabb::Bounded, bounding_hierarchy::BHShape};
+use crate::triangle::{PackHit, Triangle, TrianglePack, PACK_WIDTH};
 use mesh::Mesh;
 
 /// Combines a shape and a reference to a material. This is the basic primitive
@@ -14,9 +15,9 @@ pub struct Primitive<'msh> {
     pub shape: Triangle<'msh>,
     pub material: Material,
 
-    /// Tracks the index of the primitives in a bounding volume
-    /// hierarchy if it is stored in one.
-    bvh_node_index: usize,
+    /// The index of the shape's mesh among the meshes in the scene. Scenes
+    /// made from a single mesh leave this as 0.
+    pub mesh_index: usize,
 }
 
 impl<'msh> Primitive<'msh> {
@@ -24,24 +25,93 @@ impl<'msh> Primitive<'msh> {
         Self {
             shape,
             material,
-            bvh_node_index: 0,
+            mesh_index: 0,
         }
     }
+
+    /// Describe the primitive's surface at an interaction that's `t` along a
+    /// camera ray, for the film to record.
+    pub fn visible_surface(&self, t: f32, interaction: &SurfaceInteraction) -> VisibleSurface {
+        VisibleSurface::new(
+            t,
+            interaction,
+            self.material.color,
+            self.shape.0.index_in_mesh,
+            self.mesh_index,
+        )
+    }
 }
 
 impl<'msh> Bounded for Primitive<'msh> {
-    fn aabb(&self) -> bvh::aabb::AABB {
-        self.shape.aabb()
+    fn world_bounds(&self) -> Bounds3<f32> {
+        self.shape.world_bounds()
     }
 }
 
-impl<'msh> BHShape for Primitive<'msh> {
-    fn set_bh_node_index(&mut self, index: usize) {
-        self.bvh_node_index = index;
+/// Up to `PACK_WIDTH` primitives whose triangles are tested against a ray all
+/// at once.
+#[derive(Clone, Copy)]
+pub struct PrimitivePack<'msh> {
+    triangles: TrianglePack,
+
+    /// The primitives in the pack. Unused entries repeat the first primitive.
+    primitives: [Primitive<'msh>; PACK_WIDTH],
+
+    /// The number of primitives in the pack.
+    len: usize,
+}
+
+impl<'msh> PrimitivePack<'msh> {
+    /// Panics if there are no primitives or more than `PACK_WIDTH`
+    /// primitives.
+    pub fn new(primitives: &[Primitive<'msh>]) -> Self {
+        let shapes: Vec<Triangle> = primitives.iter().map(|p| p.shape).collect();
+        let triangles = TrianglePack::new(&shapes);
+        let mut padded = [primitives[0]; PACK_WIDTH];
+        padded[..primitives.len()].copy_from_slice(primitives);
+        Self {
+            triangles,
+            primitives: padded,
+            len: primitives.len(),
+        }
+    }
+
+    /// Return the primitives in the pack.
+    pub fn primitives(&self) -> &[Primitive<'msh>] {
+        &self.primitives[..self.len]
     }
 
-    fn bh_node_index(&self) -> usize {
-        self.bvh_node_index
+    // Find the first primitive in the pack that the ray intersects.
+    pub fn ray_intersection(
+        &self,
+        ray: &Ray,
+    ) -> Option<(f32, Primitive<'msh>, SurfaceInteraction)> {
+        let hits = self.triangles.ray_hits(ray);
+        // Visit the hits from nearest to farthest. Among hits at the same
+        // distance, prefer the later primitive, as testing the primitives one
+        // at a time and shortening the ray after each hit would.
+        let mut lanes = [(0, PackHit::default()); PACK_WIDTH];
+        let mut hit_count = 0;
+        for (lane, hit) in hits.iter().enumerate() {
+            if let Some(hit) = hit {
+                lanes[hit_count] = (lane, *hit);
+                hit_count += 1;
+            }
+        }
+        let lanes = &mut lanes[..hit_count];
+        lanes.sort_unstable_by(|(l1, h1), (l2, h2)| {
+            number::f32::total_cmp(&h1.t, &h2.t).then(l2.cmp(l1))
+        });
+
+        // Computing the surface interaction fails only for degenerate
+        // triangles, in which case the next hit is used instead.
+        lanes.iter().find_map(|&(lane, hit)| {
+            let p = self.primitives[lane];
+            let [b0, b1, b2] = hit.barycentrics;
+            p.shape
+                .surface_interaction(b0, b1, b2, ray.direction)
+                .map(|interaction| (hit.t, p, interaction))
+        })
     }
 }
 
@@ -49,7 +119,8 @@ impl<'msh> BHShape for Primitive<'msh> {
 pub enum PrimitiveAggregate<'msh> {
     Primitive(Primitive<'msh>),
     Vector(Vec<PrimitiveAggregate<'msh>>),
-    Bvh(Vec<Primitive<'msh>>, BVH),
+    Bvh(Bvh<Primitive<'msh>>),
+    PackedBvh(Bvh<PrimitivePack<'msh>>),
 }
 
 impl<'msh> PrimitiveAggregate<'msh> {
@@ -71,32 +142,136 @@ impl<'msh> PrimitiveAggregate<'msh> {
                 .map(|(t, interaction)| (t, *p, interaction)),
             PrimitiveAggregate::Vector(ps) => ps
                 .iter()
-                .filter_map(|r| {
-                    r.ray_intersection(ray)
-                        .map(|(t, p, interaction)| (t, p, interaction))
-                })
+                .filter_map(|r| r.ray_intersection(ray))
                 .min_by(|(t1, _, _), (t2, _, _)| number::f32::total_cmp(t1, t2)),
-            PrimitiveAggregate::Bvh(ps, bvh) => {
-                let hit_primitives = bvh.traverse(&ray.into(), ps);
-                hit_primitives
-                    .iter()
-                    .filter_map(|&&p| {
-                        p.shape
-                            .ray_intersection(ray)
-                            .map(|(t, interaction)| (t, p, interaction))
-                    })
-                    .min_by(|(t1, _, _), (t2, _, _)| number::f32::total_cmp(t1, t2))
+            PrimitiveAggregate::Bvh(bvh) => bvh
+                .intersect(ray, |p, ray| {
+                    p.shape
+                        .ray_intersection(ray)
+                        .map(|(t, interaction)| (t, (*p, interaction)))
+                })
+                .map(|(t, (p, interaction))| (t, p, interaction)),
+            PrimitiveAggregate::PackedBvh(bvh) => bvh
+                .intersect(ray, |pack, ray| {
+                    pack.ray_intersection(ray)
+                        .map(|(t, p, interaction)| (t, (p, interaction)))
+                })
+                .map(|(t, (p, interaction))| (t, p, interaction)),
+        }
+    }
+
+    // Find the first primitive that each ray in a packet intersects, as
+    // `ray_intersection` would for each ray on its own. Coherent packets of
+    // rays that share a direction are traced through hierarchies together.
+    pub fn ray_intersection_packet(
+        &self,
+        rays: &[Ray],
+    ) -> Vec<Option<(f32, Primitive<'msh>, SurfaceInteraction)>> {
+        let flatten = |hits: Vec<Option<(f32, (Primitive<'msh>, SurfaceInteraction))>>| {
+            hits.into_iter()
+                .map(|hit| hit.map(|(t, (p, interaction))| (t, p, interaction)))
+                .collect()
+        };
+        match self {
+            PrimitiveAggregate::Bvh(bvh) => flatten(bvh.intersect_packet(rays, |p, ray| {
+                p.shape
+                    .ray_intersection(ray)
+                    .map(|(t, interaction)| (t, (*p, interaction)))
+            })),
+            PrimitiveAggregate::PackedBvh(bvh) => {
+                flatten(bvh.intersect_packet(rays, |pack, ray| {
+                    pack.ray_intersection(ray)
+                        .map(|(t, p, interaction)| (t, (p, interaction)))
+                }))
+            }
+            PrimitiveAggregate::Vector(ps) if self.traces_packets() => {
+                // Keep the nearest hit of each ray among the aggregates, and
+                // the first aggregate's hit among equally near hits, as
+                // `ray_intersection` does.
+                let mut nearest_hits = vec![None; rays.len()];
+                for p in ps {
+                    let hits = p.ray_intersection_packet(rays);
+                    for (nearest_hit, hit) in nearest_hits.iter_mut().zip(hits) {
+                        let is_nearer = match (&hit, &*nearest_hit) {
+                            (Some((t, _, _)), Some((nearest_t, _, _))) => {
+                                number::f32::total_cmp(t, nearest_t).is_lt()
+                            }
+                            (hit, _) => hit.is_some(),
+                        };
+                        if is_nearer {
+                            *nearest_hit = hit;
+                        }
+                    }
+                }
+                nearest_hits
             }
+            _ => rays.iter().map(|ray| self.ray_intersection(ray)).collect(),
+        }
+    }
+
+    /// Return `true` if `ray_intersection_packet` traces packets of rays
+    /// through a hierarchy together, rather than tracing each ray on its own.
+    pub fn traces_packets(&self) -> bool {
+        match self {
+            PrimitiveAggregate::Primitive(_) => false,
+            PrimitiveAggregate::Vector(ps) => ps.iter().any(|p| p.traces_packets()),
+            PrimitiveAggregate::Bvh(_) | PrimitiveAggregate::PackedBvh(_) => true,
+        }
+    }
+
+    /// Return a bounding box around every primitive in the aggregate, or
+    /// `None` if the aggregate is empty.
+    pub fn world_bounds(&self) -> Option<Bounds3<f32>> {
+        match self {
+            PrimitiveAggregate::Primitive(p) => Some(p.world_bounds()),
+            PrimitiveAggregate::Vector(ps) => ps
+                .iter()
+                .filter_map(|p| p.world_bounds())
+                .reduce(|a, b| a.union(&b)),
+            PrimitiveAggregate::Bvh(bvh) => bvh.world_bounds(),
+            PrimitiveAggregate::PackedBvh(bvh) => bvh.world_bounds(),
         }
     }
 
+    /// Return every primitive in the aggregate.
+    pub fn primitives(&self) -> Vec<Primitive<'msh>> {
+        match self {
+            PrimitiveAggregate::Primitive(p) => vec![*p],
+            PrimitiveAggregate::Vector(ps) => ps.iter().flat_map(|p| p.primitives()).collect(),
+            PrimitiveAggregate::Bvh(bvh) => bvh.primitives().to_vec(),
+            PrimitiveAggregate::PackedBvh(bvh) => bvh
+                .primitives()
+                .iter()
+                .flat_map(|pack| pack.primitives().iter().copied())
+                .collect(),
+        }
+    }
+
+    /// Create an aggregate containing each triangle in the mesh, without
+    /// organizing them for fast ray intersection tests. This avoids the cost
+    /// of building a hierarchy when the scene is rasterized rather than ray
+    /// traced.
+    pub fn list_from_mesh(mesh: &'msh Mesh, material: Material) -> Self {
+        Self::Vector(
+            mesh.triangles()
+                .into_iter()
+                .map(|t| Self::primitive(Triangle(t), material))
+                .collect(),
+        )
+    }
+
+    /// Create an aggregate containing each triangle in the mesh, organized in a
+    /// bounding volume hierarchy. The triangles in each leaf of the hierarchy
+    /// are tested against rays `PACK_WIDTH` at a time.
     pub fn from_mesh(mesh: &'msh Mesh, material: Material) -> Self {
-        let mut primitives: Vec<Primitive> = mesh
+        let primitives: Vec<Primitive> = mesh
             .triangles()
             .into_iter()
             .map(|t| Primitive::new(Triangle(t), material))
             .collect();
-        let bvh = BVH::build(&mut primitives);
-        Self::Bvh(primitives, bvh)
+        Self::PackedBvh(
+            Bvh::build_for_groups(primitives, PACK_WIDTH)
+                .group_leaves(PACK_WIDTH, PrimitivePack::new),
+        )
     }
 }
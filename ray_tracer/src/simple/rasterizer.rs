@@ -0,0 +1,477 @@
+use super::{OriginalRayTracer, Primitive, Scene};
+use crate::{
+    camera::{Camera, CameraSample, OrthographicCamera},
+    film::{Film, FilmTile},
+    filter::{Filter, FilterTable},
+    geometry::bounds::Bounds2,
+    integrator::{render_passes, Cancelled, PrimarySample, RenderOptions, Tile, TileSamples},
+    sampler::IncrementalSampler,
+};
+use cgmath::{InnerSpace, Matrix4, Point2, Point3, Transform};
+use rayon::prelude::*;
+
+/// Render the scene by rasterizing its triangles into a z-buffer, rather than
+/// by tracing rays through it.
+///
+/// The same samples are taken and filtered onto the film as `render` takes and
+/// filters with `OriginalRayTracer`, and each sample is shaded the same way as
+/// the nearest triangle that covers it, so the two produce nearly identical
+/// images. Rasterizing doesn't need an acceleration structure around the
+/// scene's primitives, which makes it much cheaper for small images.
+///
+/// * scene - The scene being rendered. Its primitives don't need to be
+///   organized in a hierarchy.
+/// * camera - Controls how the scene is viewed.
+/// * film - The film onto which the scene is rendered.
+/// * filter - The filter used to reconstruct pixels from samples.
+/// * sampler - Chooses the points on the film at which samples are taken.
+///   Every sample is taken before any of them are shaded, so adaptive
+///   samplers always take their maximum number of samples per pixel.
+/// * options - Controls how tiles of the image are scheduled, and reports
+///   progress.
+///
+/// Returns the number of passes that were added to the film, which is fewer
+/// than the number of passes in `options` if the deadline passed first.
+/// Returns `Err(Cancelled)` if the render is cancelled through `options`. The
+/// passes that were finished before then remain in the film.
+pub fn rasterize<Sampler: IncrementalSampler + Send + Sync>(
+    scene: &Scene,
+    camera: &OrthographicCamera,
+    film: &mut Film,
+    filter: &(dyn Filter + Send + Sync),
+    sampler: &Sampler,
+    options: &RenderOptions,
+) -> Result<usize, Cancelled> {
+    let image_sample_bounds = film.sample_bounds(filter.half_width(), filter.half_height());
+    let filter_table = FilterTable::new(filter);
+    let tile_size = options.tile_size.max(1);
+    let tiles = Tile::span_image_sample_bounds(&image_sample_bounds, tile_size, options.tile_order);
+
+    let world_to_raster = camera.world_to_raster();
+    let primitives = scene.primitives.primitives();
+    let triangles: Vec<RasterTriangle> = options.install(|| {
+        primitives
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, primitive)| {
+                RasterTriangle::new(index, primitive, &world_to_raster)
+            })
+            .collect()
+    });
+    let bins = bin_triangles(&triangles, &image_sample_bounds, tile_size);
+
+    let samples_per_pixel = (!sampler.is_adaptive()).then(|| sampler.samples_per_pixel());
+    render_passes(
+        film,
+        &tiles,
+        options,
+        samples_per_pixel,
+        |film, tile, tile_samples| {
+            // The sampler is seeded the same way as in `render`, so that the same
+            // samples are taken.
+            let mut sampler = sampler.clone_with_seed(tile_samples.seed);
+            let mut film_tile = film.tile(
+                &tile.sample_bounds,
+                filter_table.half_width(),
+                filter_table.half_height(),
+            )?;
+            let bin = &bins[tile.row_major_index];
+            let rasterized = RasterizedTile::new(
+                &tile.sample_bounds,
+                tile_samples,
+                &mut sampler,
+                &triangles,
+                bin,
+            );
+            rasterized.shade(scene, camera, &primitives, &mut film_tile, &filter_table);
+            Some(film_tile)
+        },
+    )
+}
+
+/// A triangle whose vertices have been transformed to raster space.
+struct RasterTriangle {
+    /// The index of the triangle's primitive in the scene's primitives.
+    primitive_index: usize,
+
+    vertices: [Point3<f32>; 3],
+
+    /// Twice the signed area of the triangle in raster space's xy plane.
+    area: f32,
+
+    /// A bounding box around the pixels that the triangle could cover.
+    pixel_bounds: Bounds2<i32>,
+}
+
+impl RasterTriangle {
+    /// Transform the primitive's triangle to raster space. Return `None` if it
+    /// can't cover any samples, because it is edge-on to the camera or entirely
+    /// behind the near clipping plane.
+    fn new(
+        primitive_index: usize,
+        primitive: &Primitive,
+        world_to_raster: &Matrix4<f32>,
+    ) -> Option<Self> {
+        let (p0, p1, p2) = primitive.shape.0.positions();
+        let vertices = [p0, p1, p2].map(|p| world_to_raster.transform_point(p));
+        let [v0, v1, v2] = vertices;
+        if v0.z <= 0.0 && v1.z <= 0.0 && v2.z <= 0.0 {
+            return None;
+        }
+
+        let area = edge_function(&v0, &v1, v2.x, v2.y);
+        if area == 0.0 || !area.is_finite() {
+            return None;
+        }
+
+        let min_x = v0.x.min(v1.x).min(v2.x);
+        let min_y = v0.y.min(v1.y).min(v2.y);
+        let max_x = v0.x.max(v1.x).max(v2.x);
+        let max_y = v0.y.max(v1.y).max(v2.y);
+        let pixel_bounds = Bounds2::new(
+            Point2::new(min_x.floor() as i32, min_y.floor() as i32),
+            Point2::new(max_x.floor() as i32 + 1, max_y.floor() as i32 + 1),
+        );
+
+        Some(Self {
+            primitive_index,
+            vertices,
+            area,
+            pixel_bounds,
+        })
+    }
+
+    /// Return the barycentric coordinates and raster space depth of the point
+    /// on the triangle that a sample at `film_point` sees, if the triangle
+    /// covers the sample.
+    fn fragment_at(&self, film_point: &Point2<f32>) -> Option<([f32; 3], f32)> {
+        let [v0, v1, v2] = &self.vertices;
+        let w0 = edge_function(v1, v2, film_point.x, film_point.y);
+        let w1 = edge_function(v2, v0, film_point.x, film_point.y);
+        let w2 = edge_function(v0, v1, film_point.x, film_point.y);
+
+        // Points on an edge are covered by both triangles that share the edge,
+        // so that no gaps open up between them.
+        let inside_counterclockwise = w0 >= 0.0 && w1 >= 0.0 && w2 >= 0.0;
+        let inside_clockwise = w0 <= 0.0 && w1 <= 0.0 && w2 <= 0.0;
+        if !inside_counterclockwise && !inside_clockwise {
+            return None;
+        }
+
+        let inv_area = 1.0 / self.area;
+        let b = [w0 * inv_area, w1 * inv_area, w2 * inv_area];
+        let depth = b[0] * v0.z + b[1] * v1.z + b[2] * v2.z;
+        Some((b, depth))
+    }
+}
+
+/// Return twice the signed area of the triangle formed by `a`, `b`, and the
+/// point (x, y) in raster space's xy plane.
+fn edge_function(a: &Point3<f32>, b: &Point3<f32>, x: f32, y: f32) -> f32 {
+    (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+}
+
+/// Return the indices of the triangles that could cover samples in each tile
+/// returned by `Tile::span_image_sample_bounds`, indexed by the tiles'
+/// row-major indices.
+fn bin_triangles(
+    triangles: &[RasterTriangle],
+    image_sample_bounds: &Bounds2<i32>,
+    tile_size: usize,
+) -> Vec<Vec<usize>> {
+    let (tile_count_x, tile_count_y) = Tile::tile_counts(image_sample_bounds, tile_size);
+    let mut bins = vec![vec![]; tile_count_x * tile_count_y];
+    for (index, triangle) in triangles.iter().enumerate() {
+        let bounds = match triangle.pixel_bounds.intersect(image_sample_bounds) {
+            Some(bounds) if bounds.min.x < bounds.max.x && bounds.min.y < bounds.max.y => bounds,
+            _ => continue,
+        };
+        let tile_index = |pixel: i32, image_min: i32| (pixel - image_min) as usize / tile_size;
+        let min_x = tile_index(bounds.min.x, image_sample_bounds.min.x);
+        let min_y = tile_index(bounds.min.y, image_sample_bounds.min.y);
+        let max_x = tile_index(bounds.max.x - 1, image_sample_bounds.min.x).min(tile_count_x - 1);
+        let max_y = tile_index(bounds.max.y - 1, image_sample_bounds.min.y).min(tile_count_y - 1);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                bins[y * tile_count_x + x].push(index);
+            }
+        }
+    }
+    bins
+}
+
+/// The nearest triangle that covers a sample.
+#[derive(Debug, Clone, Copy)]
+struct Fragment {
+    primitive_index: usize,
+    barycentric: [f32; 3],
+    depth: f32,
+}
+
+/// The samples in a tile, along with the nearest fragment that each sample
+/// sees.
+struct RasterizedTile {
+    samples: Vec<CameraSample>,
+    fragments: Vec<Option<Fragment>>,
+}
+
+impl RasterizedTile {
+    /// Take every sample in the tile, in the same order as `render` takes
+    /// them, and find the nearest of the triangles that covers each one.
+    ///
+    /// * sample_bounds - The tile's sample bounds.
+    /// * tile_samples - The samples that the tile takes in each pixel.
+    /// * sampler - Generates the samples.
+    /// * triangles - Every triangle in the scene.
+    /// * bin - The indices of the triangles that could cover the tile's
+    ///   samples.
+    fn new<Sampler: IncrementalSampler>(
+        sample_bounds: &Bounds2<i32>,
+        tile_samples: TileSamples,
+        sampler: &mut Sampler,
+        triangles: &[RasterTriangle],
+        bin: &[usize],
+    ) -> Self {
+        // Each pixel's samples are stored contiguously, so that a triangle only
+        // needs to be tested against the samples in the pixels it might cover.
+        let width = (sample_bounds.max.x - sample_bounds.min.x) as usize;
+        let mut samples = vec![];
+        let mut first_samples = vec![];
+        for pixel_min_corner in sample_bounds.range() {
+            first_samples.push(samples.len());
+            sampler.start_pixel_sample(pixel_min_corner, tile_samples.first_sample);
+            loop {
+                samples.push(sampler.get_camera_sample(pixel_min_corner));
+                let sample_count = samples.len() - first_samples.last().unwrap();
+                if tile_samples.sample_count == Some(sample_count) || !sampler.start_next_sample() {
+                    break;
+                }
+            }
+        }
+        first_samples.push(samples.len());
+
+        let mut fragments: Vec<Option<Fragment>> = vec![None; samples.len()];
+        for &index in bin {
+            let triangle = &triangles[index];
+            let bounds = match triangle.pixel_bounds.intersect(sample_bounds) {
+                Some(bounds) => bounds,
+                None => continue,
+            };
+            for y in bounds.min.y..bounds.max.y {
+                for x in bounds.min.x..bounds.max.x {
+                    let pixel_index = (y - sample_bounds.min.y) as usize * width
+                        + (x - sample_bounds.min.x) as usize;
+                    let pixel_samples = first_samples[pixel_index]..first_samples[pixel_index + 1];
+                    for sample_index in pixel_samples {
+                        let (barycentric, depth) =
+                            match triangle.fragment_at(&samples[sample_index].film_point) {
+                                Some(fragment) => fragment,
+                                None => continue,
+                            };
+                        // Points at or in front of the near clipping plane
+                        // can't be seen, just as rays from the camera only
+                        // hit surfaces beyond their origins.
+                        if depth <= 0.0 {
+                            continue;
+                        }
+                        let nearest = &mut fragments[sample_index];
+                        if nearest.is_none_or(|nearest| depth < nearest.depth) {
+                            *nearest = Some(Fragment {
+                                primitive_index: triangle.primitive_index,
+                                barycentric,
+                                depth,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        Self { samples, fragments }
+    }
+
+    /// Shade each sample's fragment and add the samples to the film tile.
+    fn shade(
+        &self,
+        scene: &Scene,
+        camera: &OrthographicCamera,
+        primitives: &[Primitive],
+        film_tile: &mut FilmTile,
+        filter: &FilterTable,
+    ) {
+        for (sample, fragment) in self.samples.iter().zip(&self.fragments) {
+            let (ray, weight) = camera.generate_ray(sample);
+            let primary_sample = match fragment {
+                Some(fragment) if weight > 0.0 => {
+                    let primitive = &primitives[fragment.primitive_index];
+                    let [b0, b1, b2] = fragment.barycentric;
+                    match primitive
+                        .shape
+                        .surface_interaction(b0, b1, b2, ray.direction)
+                    {
+                        Some(interaction) => {
+                            // The parametric distance along the ray that would
+                            // have hit the fragment.
+                            let t = (interaction.point - ray.origin).dot(ray.direction)
+                                / ray.direction.magnitude2();
+                            PrimarySample {
+                                radiance: OriginalRayTracer::shade_surface_interaction(
+                                    scene,
+                                    &interaction,
+                                    &primitive.material,
+                                ),
+                                visible_surface: Some(primitive.visible_surface(t, &interaction)),
+                                aovs: Vec::new(),
+                            }
+                        }
+                        None => PrimarySample::transparent(),
+                    }
+                }
+                _ => PrimarySample::transparent(),
+            };
+            film_tile.add_sample(
+                &sample.film_point,
+                &primary_sample.radiance,
+                weight,
+                filter,
+                primary_sample.visible_surface.as_ref(),
+                &primary_sample.aovs,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod rasterizer_tests {
+    use super::rasterize;
+    use crate::{
+        camera::OrthographicCamera,
+        color::RgbaSpectrum,
+        film::{Channel, Film, ALBEDO},
+        filter::MitchellFilter,
+        integrator::RenderOptions,
+        light::Light,
+        render,
+        sampler::StratifiedSampler,
+        simple::{Material, OriginalRayTracer, PrimitiveAggregate, Scene},
+    };
+    use cgmath::{Matrix4, Point3, SquareMatrix, Vector2, Vector3};
+    use mesh::MeshBuilder;
+
+    #[test]
+    fn matches_the_ray_tracer() {
+        // A tetrahedron in front of the camera, so that some triangles occlude
+        // others.
+        let positions = vec![
+            Point3::new(-0.8, -0.6, 1.0),
+            Point3::new(0.7, -0.7, 1.2),
+            Point3::new(0.0, 0.8, 0.9),
+            Point3::new(0.1, 0.0, 0.3),
+        ];
+        let normals = vec![Vector3::new(0.0, 0.0, -1.0); 4];
+        let indices = vec![(0, 1, 2), (0, 1, 3), (1, 2, 3), (2, 0, 3)];
+        let mesh = MeshBuilder::new(positions, normals, indices).build();
+        let material = Material::new(
+            RgbaSpectrum::from_rgb(0.9, 0.5, 0.2),
+            0.1,
+            0.7,
+            0.3,
+            20.0,
+            0.0,
+        );
+        let lights = || {
+            vec![Light::point_light(
+                Point3::new(-2.0, 2.0, -2.0),
+                RgbaSpectrum::from_rgb(1.0, 1.0, 1.0),
+            )]
+        };
+
+        let resolution = Vector2::new(32, 32);
+        let camera = OrthographicCamera::new(
+            Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0)),
+            0.0,
+            10.0,
+            Vector2::new(2.0, 2.0),
+            resolution,
+        );
+        let filter = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
+        let sampler = StratifiedSampler::new(2, 2, 5, 0, true);
+        let film = || {
+            Film::new(resolution)
+                .with_channel(Channel::depth())
+                .with_channel(Channel::normal())
+                .with_channel(Channel::albedo())
+                .with_channel(Channel::triangle_index())
+        };
+
+        let traced_scene = Scene::new(PrimitiveAggregate::from_mesh(&mesh, material), lights());
+        let mut traced = film();
+        render(
+            &traced_scene,
+            &camera,
+            &mut traced,
+            &filter,
+            &sampler,
+            &OriginalRayTracer {},
+            5,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        let rasterized_scene = Scene::new(
+            PrimitiveAggregate::list_from_mesh(&mesh, material),
+            lights(),
+        );
+        let mut rasterized = film();
+        rasterize(
+            &rasterized_scene,
+            &camera,
+            &mut rasterized,
+            &filter,
+            &sampler,
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        // Both record the depth of the nearest surface in each pixel.
+        let traced_depth = traced.write_depth().unwrap();
+        let rasterized_depth = rasterized.write_depth().unwrap();
+        for (a, b) in traced_depth.pixels().zip(rasterized_depth.pixels()) {
+            assert_eq!(a.0[0].is_finite(), b.0[0].is_finite());
+            if a.0[0].is_finite() {
+                assert!((a.0[0] - b.0[0]).abs() < 1e-4);
+            }
+        }
+        assert!(traced_depth.pixels().any(|p| p.0[0].is_finite()));
+        let traced_normals = traced.write_normals(&Matrix4::identity()).unwrap();
+        let rasterized_normals = rasterized.write_normals(&Matrix4::identity()).unwrap();
+        for (a, b) in traced_normals.pixels().zip(rasterized_normals.pixels()) {
+            assert!(a.0.iter().zip(&b.0).all(|(a, b)| (a - b).abs() < 1e-4));
+        }
+        let traced_albedo = traced.write_vector_channel(ALBEDO).unwrap();
+        let rasterized_albedo = rasterized.write_vector_channel(ALBEDO).unwrap();
+        for (a, b) in traced_albedo.pixels().zip(rasterized_albedo.pixels()) {
+            assert!(a.0.iter().zip(&b.0).all(|(a, b)| (a - b).abs() < 1e-4));
+        }
+        assert_eq!(
+            traced.write_triangle_indices().unwrap(),
+            rasterized.write_triangle_indices().unwrap()
+        );
+
+        let traced = traced.write_image();
+        let rasterized = rasterized.write_image();
+        let differences = traced
+            .pixels()
+            .zip(rasterized.pixels())
+            .map(|(a, b)| {
+                a.0.iter()
+                    .zip(&b.0)
+                    .map(|(a, b)| (*a as i32 - *b as i32).abs())
+                    .max()
+                    .unwrap()
+            })
+            .collect::<Vec<i32>>();
+        assert!(differences.iter().all(|&d| d <= 1));
+        assert!(traced.pixels().any(|p| p.0[3] == 255));
+    }
+}
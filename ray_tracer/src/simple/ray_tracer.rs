@@ -1,10 +1,15 @@
 use crate::{
-    color::RgbaSpectrum, geometry::vector, integrator::RayTracer, interaction::SurfaceInteraction,
-    light::Light, ray::Ray, sampler::IncrementalSampler,
+    color::RgbaSpectrum,
+    geometry::vector,
+    integrator::{PrimarySample, RayTracer},
+    interaction::SurfaceInteraction,
+    light::Light,
+    ray::Ray,
+    sampler::IncrementalSampler,
 };
 use cgmath::InnerSpace;
 
-use super::{Material, Scene};
+use super::{Material, Primitive, Scene};
 
 pub struct OriginalRayTracer {}
 
@@ -19,6 +24,34 @@ impl<'msh, Sampler: IncrementalSampler> RayTracer<Scene<'msh>, Sampler> for Orig
     ) -> RgbaSpectrum {
         Self::color_at(scene, ray)
     }
+
+    fn primary_sample(
+        &self,
+        ray: &Ray,
+        scene: &Scene<'msh>,
+        _sampler: &mut Sampler,
+        _max_depth: usize,
+    ) -> PrimarySample {
+        Self::primary_sample_for_hit(scene, scene.ray_intersection(ray))
+    }
+
+    fn traces_primary_ray_packets(&self, scene: &Scene<'msh>) -> bool {
+        scene.primitives.traces_packets()
+    }
+
+    fn primary_sample_packet(
+        &self,
+        rays: &[Ray],
+        scene: &Scene<'msh>,
+        _sampler: &mut Sampler,
+        _max_depth: usize,
+    ) -> Vec<PrimarySample> {
+        scene
+            .ray_intersection_packet(rays)
+            .into_iter()
+            .map(|hit| Self::primary_sample_for_hit(scene, hit))
+            .collect()
+    }
 }
 
 impl OriginalRayTracer {
@@ -30,6 +63,22 @@ impl OriginalRayTracer {
         }
     }
 
+    /// Shade the first hit of a primary ray, if any, and describe the surface
+    /// that was hit.
+    fn primary_sample_for_hit(
+        scene: &Scene,
+        hit: Option<(f32, Primitive, SurfaceInteraction)>,
+    ) -> PrimarySample {
+        match hit {
+            Some((t, primitive, interaction)) => PrimarySample {
+                radiance: Self::shade_surface_interaction(scene, &interaction, &primitive.material),
+                visible_surface: Some(primitive.visible_surface(t, &interaction)),
+                aovs: Vec::new(),
+            },
+            None => PrimarySample::transparent(),
+        }
+    }
+
     pub fn shade_surface_interaction(
         scene: &Scene,
         interaction: &SurfaceInteraction,
@@ -1,5 +1,8 @@
 use super::primitive::{Primitive, PrimitiveAggregate};
-use crate::{interaction::SurfaceInteraction, light::Light, ray::Ray};
+use crate::{
+    geometry::bounds::Bounds3, integrator::BoundedScene, interaction::SurfaceInteraction,
+    light::Light, ray::Ray,
+};
 
 pub struct Scene<'msh> {
     pub primitives: PrimitiveAggregate<'msh>,
@@ -20,4 +23,18 @@ impl<'msh> Scene<'msh> {
     ) -> Option<(f32, Primitive<'msh>, SurfaceInteraction)> {
         self.primitives.ray_intersection(ray)
     }
+
+    // Find the first primitive that each ray in a packet intersects.
+    pub fn ray_intersection_packet(
+        &self,
+        rays: &[Ray],
+    ) -> Vec<Option<(f32, Primitive<'msh>, SurfaceInteraction)>> {
+        self.primitives.ray_intersection_packet(rays)
+    }
+}
+
+impl<'msh> BoundedScene for Scene<'msh> {
+    fn world_bounds(&self) -> Option<Bounds3<f32>> {
+        self.primitives.world_bounds()
+    }
 }
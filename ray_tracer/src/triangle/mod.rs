@@ -1,7 +1,11 @@
-use crate::geometry::{axis::Axis3, point, vector};
+mod pack;
+
+pub use pack::{PackHit, TrianglePack, PACK_WIDTH};
+
+use crate::bvh::Bounded;
+use crate::geometry::{axis::Axis3, bounds::Bounds3, point, vector};
 use crate::number::efloat;
 use crate::{interaction::SurfaceInteraction, ray::Ray};
-use bvh::aabb::Bounded;
 use cgmath::{InnerSpace, Point3, Vector3};
 
 #[derive(Debug, Clone, Copy)]
@@ -13,6 +17,7 @@ impl<'msh> Triangle<'msh> {
     ///
     /// The triangle's vertex positions are in world space, `ray` is in world
     /// space, and the returned surface interaction is in world space.
+    #[allow(clippy::neg_multiply)]
     pub fn ray_intersection(&self, ray: &Ray) -> Option<(f32, SurfaceInteraction)> {
         let (p0, p1, p2) = self.0.positions();
         // let (uv0, uv1, uv2) = self.0.uvs();
@@ -129,14 +134,34 @@ impl<'msh> Triangle<'msh> {
             return None;
         }
 
-        // Compute partial derivatives.
-        let (dpdu, dpdv) = self.partial_derivatives()?;
-
         // Compute baycentric coordinates.
         let b0 = e0 * inv_det;
         let b1 = e1 * inv_det;
         let b2 = e2 * inv_det;
 
+        let interaction = self.surface_interaction(b0, b1, b2, ray.direction)?;
+        Some((t, interaction))
+    }
+
+    /// Return a description of the interaction between a ray and the triangle
+    /// at the point with the given barycentric coordinates, or `None` if the
+    /// triangle is degenerate.
+    ///
+    /// * b0, b1, b2 - The barycentric coordinates of the point, which weight
+    ///   the triangle's first, second, and third vertices, respectively.
+    /// * ray_direction - The direction of the ray in world space.
+    pub fn surface_interaction(
+        &self,
+        b0: f32,
+        b1: f32,
+        b2: f32,
+        ray_direction: Vector3<f32>,
+    ) -> Option<SurfaceInteraction> {
+        let (p0, p1, p2) = self.0.positions();
+
+        // Compute partial derivatives.
+        let (dpdu, dpdv) = self.partial_derivatives()?;
+
         // Compute error bounds for triangle intersection
         let x_abs_sum = (b0 * p0.x).abs() + (b1 * p1.x).abs() + (b2 * p2.x).abs();
         let y_abs_sum = (b0 * p0.y).abs() + (b1 * p1.y).abs() + (b2 * p2.y).abs();
@@ -158,22 +183,21 @@ impl<'msh> Triangle<'msh> {
             };
 
         // Fill in SurfaceInteraction for triangle hit
-        let interaction = SurfaceInteraction::new_with_normal(
+        Some(SurfaceInteraction::new_with_normal(
             p_hit,
             p_error,
-            -1.0 * ray.direction,
+            -1.0 * ray_direction,
             dpdu,
             dpdv,
             normal,
-        );
-
-        Some((t, interaction))
+        ))
     }
 
     /// Calculates the partial derivatives of (x,y,z) positions on the triangle with
     /// respect to the texture coordinates, u and v. Returns the vectors
     /// (δx/δu,δy/δu,δz/δu) and (δx/δv,δy/δv,δz/δv) if the triangle is not
     /// degenerate.
+    #[allow(clippy::neg_multiply)]
     fn partial_derivatives(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
         let (p0, p1, p2) = self.0.positions();
         let (uv0, uv1, uv2) = self.0.uvs();
@@ -214,18 +238,8 @@ impl<'msh> Triangle<'msh> {
 }
 
 impl<'msh> Bounded for Triangle<'msh> {
-    fn aabb(&self) -> bvh::aabb::AABB {
+    fn world_bounds(&self) -> Bounds3<f32> {
         let (v0, v1, v2) = self.0.positions();
-        let min = bvh::Point3::new(
-            v0.x.min(v1.x).min(v2.x),
-            v0.y.min(v1.y).min(v2.y),
-            v0.z.min(v1.z).min(v2.z),
-        );
-        let max = bvh::Point3::new(
-            v0.x.max(v1.x).max(v2.x),
-            v0.y.max(v1.y).max(v2.y),
-            v0.z.max(v1.z).max(v2.z),
-        );
-        bvh::aabb::AABB::with_bounds(min, max)
+        Bounds3::from_points(&[v0, v1, v2])
     }
 }
@@ -1,13 +1,92 @@
+use crate::{Error, OutputFormat};
+use image::{ImageBuffer, Rgba};
+use rayon::ThreadPool;
+use std::fmt;
+use std::sync::{atomic::AtomicBool, Arc};
+use std::time::Duration;
+
+/// The maximum number of times that a ray is allowed to bounce off of the mesh
+/// when the configuration doesn't set one.
+pub(super) const DEFAULT_MAX_DEPTH: usize = 5;
+
+/// A function that is called with the number of tiles of the image that have
+/// been rendered and the total number of tiles.
+pub(super) type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// A function that is called with the number of finished passes and a snapshot
+/// of the image after each pass.
+pub(super) type SnapshotCallback = Arc<dyn Fn(usize, ImageBuffer<Rgba<u8>, Vec<u8>>) + Send + Sync>;
+
 /// A configuration that describes how to render a mesh.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Config {
     pub(super) width: usize,
     pub(super) height: usize,
     pub(super) crop: bool,
+
+    /// Whether to write an image marking pixels with non-finite samples.
+    pub(super) non_finite_image: bool,
+
+    /// Whether to write an image of the depth of the mesh in each pixel.
+    pub(super) depth_image: bool,
+
+    /// The space to write an image of the mesh's normals in, if any.
+    pub(super) normal_image: Option<NormalSpace>,
+
+    /// Whether to also write images of the triangle and mesh that's visible in
+    /// each pixel.
+    pub(super) index_images: bool,
     pub(super) sampler: Sampler,
+    pub(super) adaptive_sampling: Option<AdaptiveSampling>,
+    pub(super) filter: Filter,
+
+    /// The maximum number of times that a ray is allowed to bounce, if it was
+    /// set. Otherwise, it's `DEFAULT_MAX_DEPTH`.
+    pub(super) max_depth: Option<usize>,
+
+    /// The luminance that brighter samples are scaled down to, if any.
+    pub(super) max_sample_luminance: Option<f32>,
+
+    /// The strength of the denoiser that's run after rendering, if any.
+    pub(super) denoise: Option<f32>,
+
+    /// The number of stops by which the image is brightened before it's tone
+    /// mapped.
+    pub(super) exposure: f32,
+    pub(super) tone_map: ToneMap,
+    pub(super) color_encoding: ColorEncoding,
+    pub(super) alpha_mode: AlphaMode,
+    pub(super) output_format: OutputFormat,
+
+    /// The color that the image is flattened onto for output formats without
+    /// an alpha channel.
+    pub(super) background: Rgb,
     pub(super) lights: Vec<Light>,
     pub(super) camera: Camera,
     pub(super) material: Material,
+    pub(super) backend: Backend,
+
+    /// The width and height of the tiles of the image that are rendered in
+    /// parallel.
+    pub(super) tile_size: usize,
+    pub(super) tile_order: TileOrder,
+
+    /// The thread pool to render in, or `None` to use rayon's global thread
+    /// pool.
+    pub(super) thread_pool: Option<Arc<ThreadPool>>,
+
+    /// The number of passes over the image, each of which adds more samples
+    /// to every pixel.
+    pub(super) passes: usize,
+    pub(super) on_snapshot: Option<SnapshotCallback>,
+
+    /// The amount of time that rendering should take at most, if any.
+    pub(super) time_budget: Option<Duration>,
+
+    pub(super) progress: Option<ProgressCallback>,
+
+    /// A flag that cancels rendering when it's set.
+    pub(super) cancellation_token: Option<Arc<AtomicBool>>,
 
     /// Indicates whether the vertex positions in the mesh assume a right hand
     /// coordinate system or a left hand coordinate system.
@@ -22,10 +101,38 @@ impl Config {
             width,
             height,
             crop: false,
+            non_finite_image: false,
+            depth_image: false,
+            normal_image: None,
+            index_images: false,
             sampler: Sampler::default(),
+            adaptive_sampling: None,
+            filter: Filter::default(),
+            max_depth: None,
+            max_sample_luminance: None,
+            denoise: None,
+            exposure: 0.0,
+            tone_map: ToneMap::default(),
+            color_encoding: ColorEncoding::default(),
+            alpha_mode: AlphaMode::default(),
+            output_format: OutputFormat::default(),
+            background: Rgb {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+            },
             lights: vec![],
             camera: Camera::default(),
             material: Material::default(),
+            backend: Backend::default(),
+            tile_size: 16,
+            tile_order: TileOrder::default(),
+            thread_pool: None,
+            passes: 1,
+            on_snapshot: None,
+            time_budget: None,
+            progress: None,
+            cancellation_token: None,
             handedness: Handedness::LeftHanded,
         }
     }
@@ -37,10 +144,38 @@ impl Config {
             width,
             height,
             crop: false,
+            non_finite_image: false,
+            depth_image: false,
+            normal_image: None,
+            index_images: false,
             sampler: Sampler::default(),
+            adaptive_sampling: None,
+            filter: Filter::default(),
+            max_depth: None,
+            max_sample_luminance: None,
+            denoise: None,
+            exposure: 0.0,
+            tone_map: ToneMap::default(),
+            color_encoding: ColorEncoding::default(),
+            alpha_mode: AlphaMode::default(),
+            output_format: OutputFormat::default(),
+            background: Rgb {
+                r: 1.0,
+                g: 1.0,
+                b: 1.0,
+            },
             lights: vec![],
             camera: Camera::default(),
             material: Material::default(),
+            backend: Backend::default(),
+            tile_size: 16,
+            tile_order: TileOrder::default(),
+            thread_pool: None,
+            passes: 1,
+            on_snapshot: None,
+            time_budget: None,
+            progress: None,
+            cancellation_token: None,
             handedness: Handedness::RightHanded,
         }
     }
@@ -81,6 +216,278 @@ impl Config {
         self
     }
 
+    /// Updates the configuration to take samples from a grid of
+    /// `x_strata_count` by `y_strata_count` strata in each pixel, which is the
+    /// default sampler with two strata in each direction.
+    ///
+    /// When `jitter` is `true`, each sample is placed randomly in its stratum.
+    /// Otherwise each sample is placed in the middle of its stratum, which
+    /// makes the rendering deterministic, but trades anti-aliasing quality for
+    /// regular patterns on edges.
+    pub fn stratified_sampler(
+        mut self,
+        x_strata_count: usize,
+        y_strata_count: usize,
+        jitter: bool,
+    ) -> Self {
+        self.sampler = Sampler::Stratified {
+            x_strata_count,
+            y_strata_count,
+            jitter,
+        };
+        self
+    }
+
+    /// Updates the configuration to take samples from the Halton sequence,
+    /// which spreads the samples of each pixel more evenly than jittered
+    /// strata, for any number of samples per pixel.
+    pub fn halton_sampler(mut self, samples_per_pixel: usize) -> Self {
+        self.sampler = Sampler::Halton { samples_per_pixel };
+        self
+    }
+
+    /// Updates the configuration to take samples from the Sobol sequence. The
+    /// samples per pixel are rounded up to a power of two, where the samples
+    /// of each pixel are spread most evenly.
+    pub fn sobol_sampler(mut self, samples_per_pixel: usize) -> Self {
+        self.sampler = Sampler::Sobol { samples_per_pixel };
+        self
+    }
+
+    /// Updates the configuration to reconstruct pixels with a Mitchell filter,
+    /// which is the default filter, with a radius of 2 and `b` and `c` of 1/3.
+    ///
+    /// Each sample contributes to the pixels within `x_radius` and `y_radius`
+    /// of it, so a smaller radius renders faster and sharper images. `b` and
+    /// `c` trade blurring, for large values of `b`, against ringing, for large
+    /// values of `c`. They usually satisfy `b + 2c = 1`.
+    pub fn mitchell_filter(mut self, x_radius: f32, y_radius: f32, b: f32, c: f32) -> Self {
+        self.filter = Filter::Mitchell {
+            x_radius,
+            y_radius,
+            b,
+            c,
+        };
+        self
+    }
+
+    /// Updates the configuration to reconstruct pixels with a box filter, which
+    /// weights every sample within `x_radius` and `y_radius` of a pixel's
+    /// center equally. With a radius of 0.5, each pixel only includes the
+    /// samples inside it, which gives crisp, pixel-exact edges.
+    pub fn box_filter(mut self, x_radius: f32, y_radius: f32) -> Self {
+        self.filter = Filter::Box { x_radius, y_radius };
+        self
+    }
+
+    /// Updates the configuration to reconstruct pixels with a triangle filter,
+    /// whose weight falls off linearly to zero at `x_radius` and `y_radius`
+    /// from a pixel's center.
+    pub fn triangle_filter(mut self, x_radius: f32, y_radius: f32) -> Self {
+        self.filter = Filter::Triangle { x_radius, y_radius };
+        self
+    }
+
+    /// Updates the configuration to reconstruct pixels with a Gaussian filter,
+    /// which gives smooth edges at the cost of some blurring. Smaller values
+    /// of `alpha` fall off more slowly and blur more.
+    pub fn gaussian_filter(mut self, x_radius: f32, y_radius: f32, alpha: f32) -> Self {
+        self.filter = Filter::Gaussian {
+            x_radius,
+            y_radius,
+            alpha,
+        };
+        self
+    }
+
+    /// Updates the configuration to reconstruct pixels with a windowed sinc
+    /// filter, which keeps the image sharp at the cost of some ringing near
+    /// edges. `tau` is the number of cycles of the sinc function before it's
+    /// windowed to zero.
+    pub fn lanczos_sinc_filter(mut self, x_radius: f32, y_radius: f32, tau: f32) -> Self {
+        self.filter = Filter::LanczosSinc {
+            x_radius,
+            y_radius,
+            tau,
+        };
+        self
+    }
+
+    /// Updates the maximum number of times that a ray is allowed to bounce off
+    /// of the mesh. The default is five. Only the ray tracer backend traces
+    /// bounces, so rendering with the rasterizer backend fails with
+    /// `Error::UnsupportedByRasterizer` if this is set.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Updates the configuration to scale samples whose luminance is greater
+    /// than `max_sample_luminance` down to that luminance, which removes bright
+    /// speckles, or fireflies, caused by rare samples with very high radiance,
+    /// at the cost of darkening the image slightly.
+    pub fn max_sample_luminance(mut self, max_sample_luminance: f32) -> Self {
+        self.max_sample_luminance = Some(max_sample_luminance);
+        self
+    }
+
+    /// Updates the configuration to remove noise from the image after it's
+    /// rendered, which lets renderings with a few samples per pixel look
+    /// clean. The denoiser blurs the lighting on each surface of the mesh
+    /// without blurring the edges between them. The greater `strength` is,
+    /// the more it blurs. 1 suits renderings with a few samples per pixel.
+    pub fn denoise(mut self, strength: f32) -> Self {
+        self.denoise = Some(strength);
+        self
+    }
+
+    /// Updates the configuration to brighten the image by `exposure` stops, or
+    /// darken it if `exposure` is negative, before it's tone mapped. Each stop
+    /// doubles the brightness. The default is zero.
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Updates the configuration to clamp the brightness of each pixel to the
+    /// range of the image, so that bright highlights are blown out to white.
+    /// This is the default.
+    pub fn clamp_tone_map(mut self) -> Self {
+        self.tone_map = ToneMap::Clamp;
+        self
+    }
+
+    /// Updates the configuration to tone map the image with the Reinhard
+    /// operator, which compresses bright highlights smoothly, at the cost of
+    /// darkening and flattening the rest of the image.
+    pub fn reinhard_tone_map(mut self) -> Self {
+        self.tone_map = ToneMap::Reinhard;
+        self
+    }
+
+    /// Updates the configuration to tone map the image with the ACES filmic
+    /// curve, which keeps contrast in the midtones and rolls bright highlights
+    /// off to white like film does.
+    pub fn aces_filmic_tone_map(mut self) -> Self {
+        self.tone_map = ToneMap::AcesFilmic;
+        self
+    }
+
+    /// Updates the configuration to encode the colors of the image with the
+    /// sRGB transfer function, and to mark PNG images as sRGB, so that colors
+    /// look the same in every color-managed viewer. This is the default.
+    pub fn srgb_output(mut self) -> Self {
+        self.color_encoding = ColorEncoding::Srgb;
+        self
+    }
+
+    /// Updates the configuration to write the linear colors of the image
+    /// without encoding them, and to mark PNG images as having a gamma of 1,
+    /// which is useful when the image is processed further rather than viewed.
+    /// Linear images have visible banding in dark areas.
+    pub fn linear_output(mut self) -> Self {
+        self.color_encoding = ColorEncoding::Linear;
+        self
+    }
+
+    /// Updates the configuration to write colors that aren't multiplied by
+    /// alpha, as PNG expects. This is the default.
+    pub fn straight_alpha(mut self) -> Self {
+        self.alpha_mode = AlphaMode::Straight;
+        self
+    }
+
+    /// Updates the configuration to write colors that are multiplied by alpha,
+    /// for compositing tools that expect premultiplied alpha. Images with
+    /// premultiplied alpha look too dark around the edges of the mesh in
+    /// viewers that expect straight alpha.
+    pub fn premultiplied_alpha(mut self) -> Self {
+        self.alpha_mode = AlphaMode::Premultiplied;
+        self
+    }
+
+    /// Updates the configuration to write images in `format` with
+    /// `render_to_writer`. The default is 8-bit PNG.
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    /// Updates the color that the image is flattened onto when it's written in
+    /// an output format without an alpha channel, such as JPEG. The components
+    /// are in [0, 1] and in the image's color encoding, so that they match the
+    /// color values of image editors. The default is white.
+    pub fn background(mut self, r: f32, g: f32, b: f32) -> Self {
+        self.background = Rgb { r, g, b };
+        self
+    }
+
+    /// Updates the configuration to take more samples in pixels whose values
+    /// are noisy, such as pixels on the mesh's silhouette, and fewer in flat
+    /// regions and empty background.
+    ///
+    /// Rounds of the sampler's samples are taken in each pixel until the
+    /// standard error of the pixel's mean luminance, relative to the mean, is
+    /// at most `error_threshold`, or until `max_samples_per_pixel` samples have
+    /// been taken. Only the ray tracer backend samples adaptively, so
+    /// rendering with the rasterizer backend fails with
+    /// `Error::UnsupportedByRasterizer` if this is set.
+    pub fn adaptive_sampling(mut self, max_samples_per_pixel: usize, error_threshold: f32) -> Self {
+        self.adaptive_sampling = Some(AdaptiveSampling {
+            max_samples_per_pixel,
+            error_threshold,
+        });
+        self
+    }
+
+    /// Updates the configuration to also write an image that marks the pixels
+    /// in which samples with NaN or infinite radiance were taken, which are
+    /// dropped from the rendering. This helps to find the triangles in a mesh
+    /// that can't be shaded, such as degenerate triangles.
+    pub fn non_finite_image(mut self) -> Self {
+        self.non_finite_image = true;
+        self
+    }
+
+    /// Updates the configuration to also write an image of the depth of the
+    /// mesh in each pixel, which is the distance from the camera's near
+    /// clipping plane to the nearest point on the mesh that any sample in the
+    /// pixel hit. This can be used for fog, edge detection, or compositing the
+    /// rendering into other scenes.
+    pub fn depth_image(mut self) -> Self {
+        self.depth_image = true;
+        self
+    }
+
+    /// Updates the configuration to also write an image of the mesh's normal in
+    /// each pixel, in the coordinate system of the STL file. Like the depth
+    /// image, each pixel holds the normal of the nearest point on the mesh
+    /// that any sample in the pixel hit. This is rendered in the same pass as
+    /// the rendering itself.
+    pub fn normal_image(mut self) -> Self {
+        self.normal_image = Some(NormalSpace::World);
+        self
+    }
+
+    /// Updates the configuration to also write an image of the mesh's normal in
+    /// each pixel, like `normal_image`, but in camera space, where x points to
+    /// the right of the image, y points to the top, and z points away from the
+    /// camera.
+    pub fn camera_space_normal_image(mut self) -> Self {
+        self.normal_image = Some(NormalSpace::Camera);
+        self
+    }
+
+    /// Updates the configuration to also write images of the index of the
+    /// triangle in the STL file, and the index of its mesh, that's visible in
+    /// each pixel. Like the depth image, each pixel holds the indices of the
+    /// nearest point on the mesh that any sample in the pixel hit, so a pixel
+    /// can be mapped back to a face of the STL file.
+    pub fn index_images(mut self) -> Self {
+        self.index_images = true;
+        self
+    }
+
     /// Updates the configuration to crop transparent pixels from the edges of
     /// the rendering.
     ///
@@ -92,6 +499,130 @@ impl Config {
         self
     }
 
+    /// Updates the configuration to render the mesh by tracing rays through
+    /// it. This is the default.
+    pub fn ray_tracer_backend(mut self) -> Self {
+        self.backend = Backend::RayTracer;
+        self
+    }
+
+    /// Updates the configuration to render the mesh by rasterizing its
+    /// triangles instead of tracing rays through them.
+    ///
+    /// The rasterizer produces nearly the same image as the ray tracer, but it
+    /// doesn't build a bounding volume hierarchy around the mesh, so it's
+    /// much faster for small images of large meshes, such as thumbnails. It
+    /// doesn't trace bounces or sample adaptively, so it can't be combined
+    /// with `max_depth` or `adaptive_sampling`.
+    pub fn rasterizer_backend(mut self) -> Self {
+        self.backend = Backend::Rasterizer;
+        self
+    }
+
+    /// Updates the width and height of the square tiles that the image is
+    /// divided into and rendered in parallel. The default is 16 pixels, and a
+    /// tile size of zero is treated as one.
+    pub fn tile_size(mut self, tile_size: usize) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    /// Updates the configuration to render tiles row by row from the top of
+    /// the image. This is the default.
+    pub fn row_major_tile_order(mut self) -> Self {
+        self.tile_order = TileOrder::RowMajor;
+        self
+    }
+
+    /// Updates the configuration to render tiles in a spiral that starts at
+    /// the center of the image, where the mesh usually is.
+    pub fn spiral_tile_order(mut self) -> Self {
+        self.tile_order = TileOrder::Spiral;
+        self
+    }
+
+    /// Updates the configuration to render tiles along a Hilbert curve, which
+    /// keeps consecutively rendered tiles next to each other.
+    pub fn hilbert_tile_order(mut self) -> Self {
+        self.tile_order = TileOrder::Hilbert;
+        self
+    }
+
+    /// Updates the configuration to render in the given thread pool rather
+    /// than in rayon's global thread pool. This caps the number of threads
+    /// that the renderer uses at the number of threads in the pool.
+    pub fn thread_pool(mut self, thread_pool: Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(thread_pool);
+        self
+    }
+
+    /// Updates the number of passes that are made over the image. Each pass
+    /// takes the sampler's samples in every pixel and adds them to the
+    /// image, so the image is refined with every pass. The default is one, and
+    /// zero passes is treated as one.
+    pub fn passes(mut self, passes: usize) -> Self {
+        self.passes = passes;
+        self
+    }
+
+    /// Updates the configuration to call `callback` after each pass over the
+    /// image, with the number of finished passes and a snapshot of the image
+    /// so far. Snapshots are never cropped.
+    ///
+    /// Together with `passes`, this lets a viewer show a noisy image quickly
+    /// and refine it as rendering continues.
+    pub fn on_snapshot(
+        mut self,
+        callback: impl Fn(usize, ImageBuffer<Rgba<u8>, Vec<u8>>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_snapshot = Some(Arc::new(callback));
+        self
+    }
+
+    /// Updates the configuration to finish rendering within `time_budget`,
+    /// which is measured from the start of rendering, including the time spent
+    /// loading the mesh.
+    ///
+    /// The samples in each pixel are taken over several passes of one sample
+    /// per pixel, so a coarse image is ready quickly. Passes are added until
+    /// the configured number of samples has been taken or the budget would be
+    /// exceeded. The first pass is always finished, so that a complete image
+    /// is returned, even if that takes longer than the budget. A later pass
+    /// that's still running when the budget runs out stops after the tiles
+    /// that it has already started.
+    ///
+    /// Each pass takes the next of the sampler's samples in every pixel, so a
+    /// render that takes every sample within the budget takes the same
+    /// samples as the same render without a budget. With adaptive sampling,
+    /// each pass takes all of a pixel's samples instead.
+    pub fn time_budget(mut self, time_budget: Duration) -> Self {
+        self.time_budget = Some(time_budget);
+        self
+    }
+
+    /// Updates the configuration to call `callback` each time a tile of the
+    /// image is rendered, with the number of tiles rendered so far and the
+    /// total number of tiles in every pass. If the time budget runs out first,
+    /// it's called once more with the total reduced to the number of tiles
+    /// that were rendered.
+    ///
+    /// Tiles are rendered in parallel, so the callback may be called from
+    /// several threads at once.
+    pub fn on_progress(mut self, callback: impl Fn(usize, usize) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Updates the configuration to stop rendering once `cancellation_token`
+    /// is set, in which case rendering fails with `Error::Cancelled`.
+    ///
+    /// The token is checked before each tile of the image is rendered, so
+    /// rendering stops shortly after the token is set.
+    pub fn cancellation_token(mut self, cancellation_token: Arc<AtomicBool>) -> Self {
+        self.cancellation_token = Some(cancellation_token);
+        self
+    }
+
     /// Updates the material used to render the mesh.
     #[allow(clippy::too_many_arguments)]
     pub fn material(
@@ -115,6 +646,66 @@ impl Config {
         self.material = material;
         self
     }
+
+    /// Return an error if the configuration sets an option that its backend
+    /// doesn't support, rather than ignoring the option.
+    pub(super) fn check_backend_options(&self) -> Result<(), Error> {
+        match self.backend {
+            Backend::RayTracer => Ok(()),
+            Backend::Rasterizer if self.adaptive_sampling.is_some() => {
+                Err(Error::UnsupportedByRasterizer("adaptive_sampling"))
+            }
+            Backend::Rasterizer if self.max_depth.is_some() => {
+                Err(Error::UnsupportedByRasterizer("max_depth"))
+            }
+            Backend::Rasterizer => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("crop", &self.crop)
+            .field("non_finite_image", &self.non_finite_image)
+            .field("depth_image", &self.depth_image)
+            .field("normal_image", &self.normal_image)
+            .field("index_images", &self.index_images)
+            .field("sampler", &self.sampler)
+            .field("adaptive_sampling", &self.adaptive_sampling)
+            .field("filter", &self.filter)
+            .field("max_depth", &self.max_depth)
+            .field("max_sample_luminance", &self.max_sample_luminance)
+            .field("denoise", &self.denoise)
+            .field("exposure", &self.exposure)
+            .field("tone_map", &self.tone_map)
+            .field("color_encoding", &self.color_encoding)
+            .field("alpha_mode", &self.alpha_mode)
+            .field("output_format", &self.output_format)
+            .field("background", &self.background)
+            .field("lights", &self.lights)
+            .field("camera", &self.camera)
+            .field("material", &self.material)
+            .field("backend", &self.backend)
+            .field("tile_size", &self.tile_size)
+            .field("tile_order", &self.tile_order)
+            .field("thread_pool", &self.thread_pool)
+            .field("passes", &self.passes)
+            .field(
+                "on_snapshot",
+                &self.on_snapshot.as_ref().map(|_| "Fn(usize, ImageBuffer)"),
+            )
+            .field("time_budget", &self.time_budget)
+            .field(
+                "progress",
+                &self.progress.as_ref().map(|_| "Fn(usize, usize)"),
+            )
+            .field("cancellation_token", &self.cancellation_token)
+            .field("handedness", &self.handedness)
+            .finish()
+    }
 }
 
 /// A position in spherical coordinates.
@@ -134,16 +725,22 @@ pub(super) struct Rgb {
 
 #[derive(Debug, Clone)]
 pub(super) enum Sampler {
-    StratifiedSampler {
+    Stratified {
         x_strata_count: usize,
         y_strata_count: usize,
         jitter: bool,
     },
+    Halton {
+        samples_per_pixel: usize,
+    },
+    Sobol {
+        samples_per_pixel: usize,
+    },
 }
 
 impl Default for Sampler {
     fn default() -> Self {
-        Self::StratifiedSampler {
+        Self::Stratified {
             x_strata_count: 2,
             y_strata_count: 2,
             jitter: true,
@@ -151,6 +748,101 @@ impl Default for Sampler {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Filter {
+    Mitchell {
+        x_radius: f32,
+        y_radius: f32,
+        b: f32,
+        c: f32,
+    },
+    Box {
+        x_radius: f32,
+        y_radius: f32,
+    },
+    Triangle {
+        x_radius: f32,
+        y_radius: f32,
+    },
+    Gaussian {
+        x_radius: f32,
+        y_radius: f32,
+        alpha: f32,
+    },
+    LanczosSinc {
+        x_radius: f32,
+        y_radius: f32,
+        tau: f32,
+    },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::Mitchell {
+            x_radius: 2.0,
+            y_radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct AdaptiveSampling {
+    pub max_samples_per_pixel: usize,
+    pub error_threshold: f32,
+}
+
+/// The method used to determine which surfaces are visible from the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum Backend {
+    #[default]
+    RayTracer,
+    Rasterizer,
+}
+
+/// The operator that maps the radiance of each pixel to the range of the
+/// image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum ToneMap {
+    #[default]
+    Clamp,
+    Reinhard,
+    AcesFilmic,
+}
+
+/// The coordinate system that normals are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum NormalSpace {
+    World,
+    Camera,
+}
+
+/// The transfer function that's applied to the colors of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum ColorEncoding {
+    #[default]
+    Srgb,
+    Linear,
+}
+
+/// Whether the colors of the image are multiplied by alpha.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum AlphaMode {
+    #[default]
+    Straight,
+    Premultiplied,
+}
+
+/// The order in which the tiles of the image are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum TileOrder {
+    #[default]
+    RowMajor,
+    Spiral,
+    Hilbert,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) enum Handedness {
     LeftHanded,
@@ -0,0 +1,131 @@
+use crate::{hdr, Error};
+use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Luma, LumaA};
+use std::io::{Seek, Write};
+
+/// A file format for depth images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFormat {
+    /// PNG, with 16-bit grayscale and alpha channels. Depths are normalized so
+    /// that the nearest pixel is black and the farthest pixel is white, which
+    /// loses the scale of the depths. Pixels that don't cover the mesh are
+    /// transparent.
+    Png16,
+
+    /// Portable float map, with a 32-bit float grayscale channel that holds
+    /// the depth as it is. Pixels that don't cover the mesh are infinite.
+    Pfm,
+}
+
+/// Write a depth image from `Rendering::depth_image` to the writer in the
+/// given format.
+pub fn write_depth_image<W: Write + Seek>(
+    depth_image: &ImageBuffer<Luma<f32>, Vec<f32>>,
+    w: &mut W,
+    format: DepthFormat,
+) -> Result<(), Error> {
+    match format {
+        DepthFormat::Png16 => DynamicImage::ImageLumaA16(normalize(depth_image))
+            .write_to(w, ImageOutputFormat::Png)?,
+        DepthFormat::Pfm => hdr::write_grayscale_pfm(depth_image, w)?,
+    }
+    Ok(())
+}
+
+/// Scale the finite depths in the image to [0, 65535], with opaque alpha, and
+/// make the infinite depths transparent.
+fn normalize(depth_image: &ImageBuffer<Luma<f32>, Vec<f32>>) -> ImageBuffer<LumaA<u16>, Vec<u16>> {
+    let (min, max) = depth_image
+        .pixels()
+        .map(|depth| depth.0[0])
+        .filter(|depth| depth.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), depth| {
+            (min.min(depth), max.max(depth))
+        });
+    let scale = if max > min { 1.0 / (max - min) } else { 0.0 };
+
+    ImageBuffer::from_fn(depth_image.width(), depth_image.height(), |x, y| {
+        let depth = depth_image.get_pixel(x, y).0[0];
+        if depth.is_finite() {
+            let normalized = (depth - min) * scale;
+            LumaA([(normalized * 65535.0).round() as u16, u16::MAX])
+        } else {
+            LumaA([u16::MAX, 0])
+        }
+    })
+}
+
+#[cfg(test)]
+mod depth_tests {
+    use super::normalize;
+    use crate::test::square_stl;
+    use crate::{render, write_depth_image, Config, DepthFormat};
+    use image::{ImageBuffer, ImageFormat, Luma, LumaA};
+    use std::io::Cursor;
+
+    /// The depth of the square from the default camera, which looks at it
+    /// from a distance of one, once it's scaled to fit in a unit sphere.
+    const SQUARE_DEPTH: f32 = 1.0;
+
+    #[test]
+    fn pixels_record_the_depth_of_the_mesh() {
+        for config in [
+            Config::new_left_handed(32, 32),
+            Config::new_left_handed(32, 32).rasterizer_backend(),
+        ] {
+            let rendering = render(square_stl(), &config.depth_image()).unwrap();
+            let depth_image = rendering.depth_image.unwrap();
+            assert!((depth_image.get_pixel(16, 16).0[0] - SQUARE_DEPTH).abs() < 1e-5);
+            assert_eq!(depth_image.get_pixel(0, 0).0, [f32::INFINITY]);
+        }
+    }
+
+    #[test]
+    fn depth_pfm_holds_the_depths_as_they_are() {
+        let config = Config::new_left_handed(32, 32).depth_image();
+        let depth_image = render(square_stl(), &config).unwrap().depth_image.unwrap();
+        let mut pfm = Cursor::new(vec![]);
+        write_depth_image(&depth_image, &mut pfm, DepthFormat::Pfm).unwrap();
+        let pfm = pfm.into_inner();
+
+        let header = b"Pf\n32 32\n-1.0\n";
+        assert_eq!(&pfm[..header.len()], header);
+        // Rows are written from bottom to top.
+        let depth_at = |x: usize, y: usize| {
+            let offset = header.len() + 4 * ((31 - y) * 32 + x);
+            f32::from_le_bytes(pfm[offset..offset + 4].try_into().unwrap())
+        };
+        assert_eq!(depth_at(16, 16), depth_image.get_pixel(16, 16).0[0]);
+        assert_eq!(depth_at(0, 0), f32::INFINITY);
+    }
+
+    #[test]
+    fn depth_png_is_transparent_where_the_mesh_is_missing() {
+        let config = Config::new_left_handed(32, 32).depth_image();
+        let depth_image = render(square_stl(), &config).unwrap().depth_image.unwrap();
+        let mut png = Cursor::new(vec![]);
+        write_depth_image(&depth_image, &mut png, DepthFormat::Png16).unwrap();
+        let png = image::load_from_memory_with_format(png.get_ref(), ImageFormat::Png)
+            .unwrap()
+            .into_luma_alpha16();
+
+        assert_eq!(png, normalize(&depth_image));
+        assert_eq!(png.get_pixel(16, 16).0[1], u16::MAX);
+        assert_eq!(png.get_pixel(0, 0).0[1], 0);
+    }
+
+    #[test]
+    fn depths_are_normalized_from_nearest_to_farthest() {
+        let depths = [1.0, 3.0, 2.0, f32::INFINITY];
+        let depth_image = ImageBuffer::from_fn(4, 1, |x, _| Luma([depths[x as usize]]));
+        let normalized: Vec<_> = normalize(&depth_image).pixels().copied().collect();
+        assert_eq!(
+            normalized,
+            [
+                LumaA([0, u16::MAX]),
+                LumaA([u16::MAX, u16::MAX]),
+                LumaA([32768, u16::MAX]),
+                LumaA([u16::MAX, 0]),
+            ]
+        );
+    }
+}
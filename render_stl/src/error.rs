@@ -30,9 +30,21 @@ pub enum Error {
         source: image::ImageError,
     },
 
+    #[error(transparent)]
+    Png {
+        #[from]
+        source: png::EncodingError,
+    },
+
     #[error("cannot render mesh because it is empty")]
     EmptyMesh,
 
     #[error("image has an area of zero after cropping transparent pixels")]
     ZeroAreaImage,
+
+    #[error("rendering was cancelled")]
+    Cancelled,
+
+    #[error("the rasterizer backend does not support the {0} option")]
+    UnsupportedByRasterizer(&'static str),
 }
@@ -0,0 +1,146 @@
+use image::{ImageBuffer, Luma, Rgb, Rgba};
+use std::io::Write;
+
+/// A file format for high dynamic range images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HdrFormat {
+    /// OpenEXR, with 32-bit float RGBA channels.
+    OpenExr,
+
+    /// Portable float map, with 32-bit float RGB channels. The format has no
+    /// alpha channel, so alpha is dropped.
+    Pfm,
+}
+
+/// Write the image in the portable float map format: a text header with the
+/// image's size, followed by the rows of the image from bottom to top, as
+/// little-endian floats.
+pub(super) fn write_pfm<W: Write>(
+    image: &ImageBuffer<Rgba<f32>, Vec<f32>>,
+    w: &mut W,
+) -> std::io::Result<()> {
+    write_pfm_rows(w, "PF", image.width(), image.height(), |x, y| {
+        let [r, g, b, _] = image.get_pixel(x, y).0;
+        vec![r, g, b]
+    })
+}
+
+/// Write the RGB image in the portable float map format, like `write_pfm`.
+pub(super) fn write_rgb_pfm<W: Write>(
+    image: &ImageBuffer<Rgb<f32>, Vec<f32>>,
+    w: &mut W,
+) -> std::io::Result<()> {
+    write_pfm_rows(w, "PF", image.width(), image.height(), |x, y| {
+        image.get_pixel(x, y).0.to_vec()
+    })
+}
+
+/// Write the grayscale image in the portable float map format, like
+/// `write_pfm`.
+pub(super) fn write_grayscale_pfm<W: Write>(
+    image: &ImageBuffer<Luma<f32>, Vec<f32>>,
+    w: &mut W,
+) -> std::io::Result<()> {
+    write_pfm_rows(w, "Pf", image.width(), image.height(), |x, y| {
+        image.get_pixel(x, y).0.to_vec()
+    })
+}
+
+/// Write a portable float map with the given magic number, whose pixels'
+/// components are returned by `pixel`.
+fn write_pfm_rows<W: Write>(
+    w: &mut W,
+    magic: &str,
+    width: u32,
+    height: u32,
+    pixel: impl Fn(u32, u32) -> Vec<f32>,
+) -> std::io::Result<()> {
+    // A negative scale indicates that the floats are little-endian.
+    write!(w, "{}\n{} {}\n-1.0\n", magic, width, height)?;
+    let mut row = Vec::new();
+    for y in (0..height).rev() {
+        row.clear();
+        for x in 0..width {
+            for component in pixel(x, y) {
+                row.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+        w.write_all(&row)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod hdr_tests {
+    use super::{write_grayscale_pfm, write_pfm, write_rgb_pfm};
+    use crate::test::square_stl;
+    use crate::{render_to_hdr, render_to_hdr_writer, Config, HdrFormat};
+    use image::{ImageBuffer, ImageFormat, Luma, Rgb, Rgba};
+    use std::io::Cursor;
+
+    /// Return the bytes of the floats in little-endian order.
+    fn le_bytes(floats: &[f32]) -> Vec<u8> {
+        floats.iter().flat_map(|f| f.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn pfm_has_little_endian_rows_from_bottom_to_top() {
+        // Each pixel's components are its column, row, and an index.
+        let image = ImageBuffer::from_fn(2, 3, |x, y| {
+            Rgba([x as f32, y as f32, (y * 2 + x) as f32 + 0.5, 0.25])
+        });
+        let mut pfm = vec![];
+        write_pfm(&image, &mut pfm).unwrap();
+
+        let header = b"PF\n2 3\n-1.0\n";
+        assert_eq!(&pfm[..header.len()], header);
+        #[rustfmt::skip]
+        let pixels = le_bytes(&[
+            0.0, 2.0, 4.5, 1.0, 2.0, 5.5,
+            0.0, 1.0, 2.5, 1.0, 1.0, 3.5,
+            0.0, 0.0, 0.5, 1.0, 0.0, 1.5,
+        ]);
+        assert_eq!(pfm[header.len()..], pixels);
+    }
+
+    #[test]
+    fn rgb_and_grayscale_pfms_keep_every_component() {
+        let inf = f32::INFINITY;
+        let rgb = ImageBuffer::from_fn(1, 2, |_, y| Rgb([y as f32, -1.0, inf]));
+        let mut pfm = vec![];
+        write_rgb_pfm(&rgb, &mut pfm).unwrap();
+        let mut expected = b"PF\n1 2\n-1.0\n".to_vec();
+        expected.extend(le_bytes(&[1.0, -1.0, inf, 0.0, -1.0, inf]));
+        assert_eq!(pfm, expected);
+
+        let grayscale = ImageBuffer::from_fn(3, 1, |x, _| Luma([x as f32 * 1.5]));
+        let mut pfm = vec![];
+        write_grayscale_pfm(&grayscale, &mut pfm).unwrap();
+        let mut expected = b"Pf\n3 1\n-1.0\n".to_vec();
+        expected.extend(le_bytes(&[0.0, 1.5, 3.0]));
+        assert_eq!(pfm, expected);
+    }
+
+    #[test]
+    fn hdr_writers_write_the_rendered_radiance() {
+        let config = Config::new_left_handed(16, 16).point_light(50.0, 50.0, 50.0, 2.0, 0.0, 0.0);
+        let image = render_to_hdr(square_stl(), &config).unwrap();
+        // The light is bright enough that some radiance is above one, and the
+        // corners of the image are background.
+        assert!(image.pixels().any(|pixel| pixel.0[0] > 1.0));
+        assert_eq!(image.get_pixel(0, 0).0[3], 0.0);
+
+        let mut exr = Cursor::new(vec![]);
+        render_to_hdr_writer(square_stl(), &config, &mut exr, HdrFormat::OpenExr).unwrap();
+        let decoded = image::load_from_memory_with_format(exr.get_ref(), ImageFormat::OpenExr)
+            .unwrap()
+            .into_rgba32f();
+        assert_eq!(decoded, image);
+
+        let mut pfm = Cursor::new(vec![]);
+        render_to_hdr_writer(square_stl(), &config, &mut pfm, HdrFormat::Pfm).unwrap();
+        let mut expected = vec![];
+        write_pfm(&image, &mut expected).unwrap();
+        assert_eq!(pfm.into_inner(), expected);
+    }
+}
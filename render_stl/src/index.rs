@@ -0,0 +1,123 @@
+use crate::Error;
+use image::{ImageBuffer, Luma};
+use std::io::Write;
+
+pub use ray_tracer::film::NO_INDEX;
+
+/// Write the triangle and mesh index images from
+/// `Rendering::triangle_index_image` and `Rendering::mesh_index_image` to the
+/// writer as a binary buffer of little-endian unsigned 32-bit integers, so
+/// that it can be read into a `Uint32Array` in a browser, for example.
+///
+/// The buffer starts with the width and height of the images, followed by a
+/// pair of integers for each pixel, in rows from the top left pixel to the
+/// bottom right pixel. The first integer of each pair is the index of the
+/// triangle in the STL file and the second is the index of its mesh. Both are
+/// `NO_INDEX` in pixels that don't cover the mesh:
+///
+/// ```text
+/// width height triangle(0, 0) mesh(0, 0) triangle(1, 0) mesh(1, 0) ...
+/// ```
+///
+/// Panics if the images aren't the same size.
+pub fn write_index_buffer<W: Write>(
+    triangle_index_image: &ImageBuffer<Luma<u32>, Vec<u32>>,
+    mesh_index_image: &ImageBuffer<Luma<u32>, Vec<u32>>,
+    w: &mut W,
+) -> Result<(), Error> {
+    assert_eq!(
+        triangle_index_image.dimensions(),
+        mesh_index_image.dimensions()
+    );
+    let mut data = Vec::with_capacity(8 + 8 * triangle_index_image.len());
+    data.extend_from_slice(&triangle_index_image.width().to_le_bytes());
+    data.extend_from_slice(&triangle_index_image.height().to_le_bytes());
+    for (triangle_index, mesh_index) in triangle_index_image.pixels().zip(mesh_index_image.pixels())
+    {
+        data.extend_from_slice(&triangle_index.0[0].to_le_bytes());
+        data.extend_from_slice(&mesh_index.0[0].to_le_bytes());
+    }
+    w.write_all(&data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod index_tests {
+    use super::NO_INDEX;
+    use crate::test::{square_stl, SQUARE_CORNERS};
+    use crate::{render, write_index_buffer, Config, Rendering};
+
+    /// Return the pixel that the centroid of the square's triangle, given by
+    /// the indices of its corners, is projected to.
+    fn centroid_pixel(rendering: &Rendering, corners: [usize; 3]) -> (u32, u32) {
+        let centroid = [0, 1, 2].map(|axis| {
+            corners
+                .iter()
+                .map(|&corner| SQUARE_CORNERS[corner][axis])
+                .sum::<f32>()
+                / 3.0
+        });
+        let [x, y] = rendering.projection.unwrap().stl_to_pixel(centroid);
+        (x.floor() as u32, y.floor() as u32)
+    }
+
+    #[test]
+    fn pixels_record_the_visible_triangle_and_mesh() {
+        for config in [
+            Config::new_left_handed(32, 32),
+            Config::new_left_handed(32, 32).rasterizer_backend(),
+        ] {
+            let rendering = render(square_stl(), &config.index_images()).unwrap();
+            let triangle_indices = rendering.triangle_index_image.as_ref().unwrap();
+            let mesh_indices = rendering.mesh_index_image.as_ref().unwrap();
+
+            // `square_stl` splits the square into the triangles with corners
+            // 0, 1, 2 and 0, 2, 3.
+            for (triangle_index, corners) in [[0, 1, 2], [0, 2, 3]].into_iter().enumerate() {
+                let (x, y) = centroid_pixel(&rendering, corners);
+                assert_eq!(triangle_indices.get_pixel(x, y).0, [triangle_index as u32]);
+                assert_eq!(mesh_indices.get_pixel(x, y).0, [0]);
+            }
+            // The diagonal between the triangles runs from the bottom left of
+            // the image to the top right.
+            assert_eq!(triangle_indices.get_pixel(24, 24).0, [0]);
+            assert_eq!(triangle_indices.get_pixel(8, 8).0, [1]);
+            assert_eq!(triangle_indices.get_pixel(0, 0).0, [NO_INDEX]);
+            assert_eq!(mesh_indices.get_pixel(0, 0).0, [NO_INDEX]);
+        }
+    }
+
+    #[test]
+    fn index_buffer_interleaves_triangle_and_mesh_indices() {
+        let config = Config::new_left_handed(32, 32).index_images();
+        let rendering = render(square_stl(), &config).unwrap();
+        let mut buffer = vec![];
+        write_index_buffer(
+            rendering.triangle_index_image.as_ref().unwrap(),
+            rendering.mesh_index_image.as_ref().unwrap(),
+            &mut buffer,
+        )
+        .unwrap();
+
+        let integers: Vec<u32> = buffer
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(integers.len(), 2 + 2 * 32 * 32);
+        assert_eq!(integers[..2], [32, 32]);
+        let pixel = |x: u32, y: u32| {
+            let offset = 2 + 2 * (y * 32 + x) as usize;
+            [integers[offset], integers[offset + 1]]
+        };
+        let (x, y) = centroid_pixel(&rendering, [0, 2, 3]);
+        assert_eq!(pixel(x, y), [1, 0]);
+        assert_eq!(pixel(0, 0), [NO_INDEX, NO_INDEX]);
+    }
+
+    #[test]
+    fn index_images_are_only_written_when_asked_for() {
+        let rendering = render(square_stl(), &Config::new_left_handed(8, 8)).unwrap();
+        assert!(rendering.triangle_index_image.is_none());
+        assert!(rendering.mesh_index_image.is_none());
+    }
+}
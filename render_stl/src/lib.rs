@@ -1,82 +1,290 @@
 mod config;
+mod depth;
 mod error;
+mod hdr;
+mod index;
+mod normal;
+mod output;
+mod projection;
+mod rendering;
+mod sampler;
+#[cfg(test)]
+mod test;
 
 use cgmath::{
-    point2, point3, vec2, Deg, InnerSpace, Matrix4, Point2, Point3, Rad, Transform, Vector2,
-    Vector3,
+    point2, point3, vec2, Deg, InnerSpace, Matrix4, Point2, Point3, Rad, SquareMatrix, Transform,
+    Vector2, Vector3,
 };
 use config::Handedness;
-use image::{imageops, ImageBuffer, ImageOutputFormat, Rgba};
+use image::{imageops, DynamicImage, ImageBuffer, ImageOutputFormat, Pixel, Rgba};
 use mesh::{Mesh, MeshBuilder};
+use ray_tracer::camera::OrthographicCamera;
 use ray_tracer::color::RgbaSpectrum;
-use ray_tracer::filter::MitchellFilter;
+use ray_tracer::film::{AlphaMode, BilateralDenoiser, Channel, ColorEncoding, Film, ToneMap};
+use ray_tracer::filter::{
+    BoxFilter, Filter, GaussianFilter, LanczosSincFilter, MitchellFilter, TriangleFilter,
+};
+use ray_tracer::integrator::{PassCallback, Progress, ProgressCallback, RenderOptions, TileOrder};
 use ray_tracer::light::Light;
-use ray_tracer::sampler::StratifiedSampler;
-use ray_tracer::simple::{Material, OriginalRayTracer, PrimitiveAggregate, Scene};
-use ray_tracer::{camera::OrthographicCamera, film::Film};
+use ray_tracer::sampler::{
+    AdaptiveSampler, HaltonSampler, IncrementalSampler, SobolSampler, StratifiedSampler,
+};
+use ray_tracer::simple::{rasterize, Material, OriginalRayTracer, PrimitiveAggregate, Scene};
 use std::cmp;
 use std::f32::consts::{FRAC_PI_2, PI};
 use std::io::{Read, Seek, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use typed_arena::Arena;
 
 pub use config::Config;
+pub use depth::{write_depth_image, DepthFormat};
 pub use error::Error;
+pub use hdr::HdrFormat;
+pub use index::{write_index_buffer, NO_INDEX};
+pub use normal::{write_normal_image, NormalFormat};
+pub use output::OutputFormat;
+pub use projection::{Projection, Ray};
+pub use rendering::{RenderStats, Rendering};
 
 /// Renders the given STL file to an image.
 pub fn render_to_image<R: Read + Seek>(
     stl_file: R,
     config: &Config,
 ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
+    render(stl_file, config).map(|rendering| rendering.image)
+}
+
+/// Renders the given STL file to an image, and reports measurements taken
+/// while rendering.
+pub fn render<R: Read + Seek>(stl_file: R, config: &Config) -> Result<Rendering, Error> {
+    let (film, stats, mut projection) = render_film(stl_file, config)?;
+    let mut image = film.write_image();
+    let mut non_finite_image = config
+        .non_finite_image
+        .then(|| film.write_non_finite_image());
+    // The film records the channels that the configuration asks for, along
+    // with any that the denoiser needs.
+    let mut depth_image = if config.depth_image {
+        film.write_depth()
+    } else {
+        None
+    };
+    let mut normal_image = config
+        .normal_image
+        .and_then(|normal_space| film.write_normals(&world_to_normal_space(config, normal_space)));
+    let mut triangle_index_image = film.write_triangle_indices();
+    let mut mesh_index_image = film.write_mesh_indices();
+
+    if config.crop {
+        let (crop_bounds_min, crop_bounds_max) =
+            non_transparent_bounds(&image).ok_or(Error::ZeroAreaImage)?;
+        image = crop(&image, crop_bounds_min, crop_bounds_max);
+        non_finite_image = non_finite_image
+            .map(|non_finite_image| crop(&non_finite_image, crop_bounds_min, crop_bounds_max));
+        depth_image =
+            depth_image.map(|depth_image| crop(&depth_image, crop_bounds_min, crop_bounds_max));
+        normal_image =
+            normal_image.map(|normal_image| crop(&normal_image, crop_bounds_min, crop_bounds_max));
+        triangle_index_image = triangle_index_image.map(|triangle_index_image| {
+            crop(&triangle_index_image, crop_bounds_min, crop_bounds_max)
+        });
+        mesh_index_image = mesh_index_image
+            .map(|mesh_index_image| crop(&mesh_index_image, crop_bounds_min, crop_bounds_max));
+        projection = projection.map(|projection| projection.cropped(crop_bounds_min));
+    }
+
+    Ok(Rendering {
+        image,
+        non_finite_image,
+        depth_image,
+        normal_image,
+        triangle_index_image,
+        mesh_index_image,
+        projection,
+        stats,
+    })
+}
+
+/// Renders the given STL file to an image with 16 bits per channel.
+pub fn render_to_image_16<R: Read + Seek>(
+    stl_file: R,
+    config: &Config,
+) -> Result<ImageBuffer<Rgba<u16>, Vec<u16>>, Error> {
+    let (film, _, _) = render_film(stl_file, config)?;
+    let mut image = film.write_image_16();
+
+    if config.crop {
+        // Crop to the same pixels as `render_to_image` does.
+        let (crop_bounds_min, crop_bounds_max) =
+            non_transparent_bounds(&film.write_image()).ok_or(Error::ZeroAreaImage)?;
+        image = crop(&image, crop_bounds_min, crop_bounds_max);
+    }
+
+    Ok(image)
+}
+
+/// Renders the given STL file to a high dynamic range image of linear RGB
+/// radiance with alpha. Unlike `render_to_image`, radiance isn't clamped to
+/// [0, 1] or quantized, so the image can be relit or tone mapped later.
+///
+/// The color of partially transparent pixels is premultiplied by alpha.
+pub fn render_to_hdr<R: Read + Seek>(
+    stl_file: R,
+    config: &Config,
+) -> Result<ImageBuffer<Rgba<f32>, Vec<f32>>, Error> {
+    let (film, _, _) = render_film(stl_file, config)?;
+    let mut image = film.write_hdr();
+
+    if config.crop {
+        // Crop to the same pixels as `render_to_image` does.
+        let (crop_bounds_min, crop_bounds_max) =
+            non_transparent_bounds(&film.write_image()).ok_or(Error::ZeroAreaImage)?;
+        image = crop(&image, crop_bounds_min, crop_bounds_max);
+    }
+
+    Ok(image)
+}
+
+/// Renders the STL file to the writer as a high dynamic range image in the
+/// given format.
+pub fn render_to_hdr_writer<R: Read + Seek, W: Write + Seek>(
+    stl_file: R,
+    config: &Config,
+    w: &mut W,
+    format: HdrFormat,
+) -> Result<(), Error> {
+    let image = render_to_hdr(stl_file, config)?;
+    match format {
+        HdrFormat::OpenExr => {
+            DynamicImage::ImageRgba32F(image).write_to(w, ImageOutputFormat::OpenExr)?
+        }
+        HdrFormat::Pfm => hdr::write_pfm(&image, w)?,
+    }
+    Ok(())
+}
+
+/// Renders the given STL file to a film, and reports measurements taken while
+/// rendering, along with the projection of the mesh onto the film, if it has
+/// one.
+fn render_film<R: Read + Seek>(
+    stl_file: R,
+    config: &Config,
+) -> Result<(Film, RenderStats, Option<Projection>), Error> {
+    config.check_backend_options()?;
+    let start = Instant::now();
+    let deadline = config
+        .time_budget
+        .and_then(|time_budget| start.checked_add(time_budget));
+
     let mut mesh_arena = Arena::new();
-    let mesh = load_mesh(stl_file, &mut mesh_arena, config.handedness)?;
+    let (mesh, stl_to_world) = load_mesh(stl_file, &mut mesh_arena, config.handedness)?;
     let material = load_material(&config.material);
     let lights = config.lights.iter().map(load_light).collect();
-    let scene = Scene::new(
-        PrimitiveAggregate::Vector(vec![PrimitiveAggregate::from_mesh(mesh, material)]),
-        lights,
-    );
 
     let resolution = Vector2::new(config.width, config.height);
-    let mut film = Film::new(resolution);
+    let mut film = load_film(config, resolution);
     let camera = load_camera(&config.camera, resolution);
+    let projection = Projection::new(stl_to_world, &camera);
 
-    let filter = MitchellFilter::new(2.0, 2.0, 1.0 / 3.0, 1.0 / 3.0);
+    let filter = load_filter(&config.filter);
     let sampler = load_sampler(&config.sampler);
+    let options = load_render_options(config, sampler.samples_per_pixel(), deadline);
 
-    ray_tracer::render(
-        &scene,
-        &camera,
-        &mut film,
-        &filter,
-        &sampler,
-        &OriginalRayTracer {},
-        5,
-    );
-    let mut image = film.write_image();
+    let (bvh_build_time, trace_time, passes, packet_samples) = match config.backend {
+        config::Backend::RayTracer => {
+            let bvh_build_start = Instant::now();
+            let primitives = options.install(|| PrimitiveAggregate::from_mesh(mesh, material));
+            let bvh_build_time = bvh_build_start.elapsed();
+            let scene = Scene::new(primitives, lights);
 
-    if config.crop {
-        image = crop_to_non_transparent(&image)?;
+            let sampler = load_adaptive_sampler(sampler, config.adaptive_sampling);
+            let trace_start = Instant::now();
+            let summary = ray_tracer::render(
+                &scene,
+                &camera,
+                &mut film,
+                filter.as_ref(),
+                &sampler,
+                &OriginalRayTracer {},
+                config.max_depth.unwrap_or(config::DEFAULT_MAX_DEPTH),
+                &options,
+            )
+            .map_err(|_| Error::Cancelled)?;
+            (
+                bvh_build_time,
+                trace_start.elapsed(),
+                summary.passes,
+                summary.packet_samples,
+            )
+        }
+        config::Backend::Rasterizer => {
+            let scene = Scene::new(PrimitiveAggregate::list_from_mesh(mesh, material), lights);
+
+            let rasterize_start = Instant::now();
+            let passes = rasterize(
+                &scene,
+                &camera,
+                &mut film,
+                filter.as_ref(),
+                &sampler,
+                &options,
+            )
+            .map_err(|_| Error::Cancelled)?;
+            (Duration::ZERO, rasterize_start.elapsed(), passes, 0)
+        }
+    };
+
+    let denoise_start = Instant::now();
+    if let Some(strength) = config.denoise {
+        film.denoise(&BilateralDenoiser::new(strength));
     }
+    let denoise_time = denoise_start.elapsed();
 
-    Ok(image)
+    let stats = RenderStats {
+        bvh_build_time,
+        trace_time,
+        denoise_time,
+        passes,
+        mean_samples_per_pixel: film.mean_samples_per_pixel(),
+        non_finite_samples: film.non_finite_sample_count(),
+        packet_samples,
+    };
+    Ok((film, stats, projection))
 }
 
-/// Renders the STL file to the writer in the PNG formata.
+/// Renders the STL file to the writer in the configured output format, PNG by
+/// default. PNGs are marked with the color encoding of the image, so that
+/// color-managed viewers display them correctly.
 pub fn render_to_writer<R: Read + Seek, W: Write + Seek>(
     stl_file: R,
     config: &Config,
     w: &mut W,
 ) -> Result<(), Error> {
-    let image = render_to_image(stl_file, config)?;
-    image.write_to(w, ImageOutputFormat::Png)?;
-    Ok(())
+    match config.output_format {
+        OutputFormat::Png16 => {
+            let image = render_to_image_16(stl_file, config)?;
+            output::write_png_16(&image, config.color_encoding, w)
+        }
+        format => output::write_image(
+            render_to_image(stl_file, config)?,
+            format,
+            config.color_encoding,
+            config.alpha_mode,
+            config.background,
+            w,
+        ),
+    }
 }
 
+/// Load the mesh from the STL file and move it into world space, where it's
+/// centered at the origin and fits in a unit sphere. Return the mesh along
+/// with the transformation that moved it.
 fn load_mesh<R: Read + Seek>(
     mesh: R,
     mesh_arena: &mut Arena<Mesh>,
     handedness: Handedness,
-) -> Result<&Mesh, Error> {
+) -> Result<(&Mesh, Matrix4<f32>), Error> {
     let mut reader = std::io::BufReader::new(mesh);
     let mesh = mesh_arena.alloc(MeshBuilder::from_stl(&mut reader)?.build());
     let (bounds_min, bounds_max) = mesh.bounding_box().ok_or(Error::EmptyMesh)?;
@@ -85,13 +293,17 @@ fn load_mesh<R: Read + Seek>(
     mesh.transform(center_to_origin);
 
     let bounding_sphere_radius = max_distance_from_origin(mesh);
-    mesh.transform(Matrix4::from_scale(1.0 / bounding_sphere_radius));
+    let scale = Matrix4::from_scale(1.0 / bounding_sphere_radius);
+    mesh.transform(scale);
+    let mut stl_to_world = scale * center_to_origin;
 
     if handedness == config::Handedness::RightHanded {
-        mesh.transform_swapping_handedness(Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0));
+        let swap_handedness = Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0);
+        mesh.transform_swapping_handedness(swap_handedness);
+        stl_to_world = swap_handedness * stl_to_world;
     }
 
-    Ok(mesh)
+    Ok((mesh, stl_to_world))
 }
 
 fn load_material(material_config: &config::Material) -> Material {
@@ -153,13 +365,165 @@ fn load_camera(camera_config: &config::Camera, resolution: Vector2<usize>) -> Or
     }
 }
 
-fn load_sampler(sampler_config: &config::Sampler) -> StratifiedSampler {
+/// Return the transformation from world space to the space that normals are
+/// written in.
+fn world_to_normal_space(config: &Config, normal_space: config::NormalSpace) -> Matrix4<f32> {
+    match normal_space {
+        // The mesh was only translated and uniformly scaled into world space,
+        // which doesn't change its normals, unless it was also reflected to
+        // convert it to a left-handed coordinate system.
+        config::NormalSpace::World => match config.handedness {
+            Handedness::LeftHanded => Matrix4::identity(),
+            Handedness::RightHanded => Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0),
+        },
+        config::NormalSpace::Camera => {
+            let resolution = Vector2::new(config.width, config.height);
+            let camera = load_camera(&config.camera, resolution);
+            camera.camera_to_world.inverse_transform().unwrap()
+        }
+    }
+}
+
+fn load_film(config: &Config, resolution: Vector2<usize>) -> Film {
+    let mut film = Film::new(resolution)
+        .with_exposure(config.exposure)
+        .with_tone_map(match config.tone_map {
+            config::ToneMap::Clamp => ToneMap::Clamp,
+            config::ToneMap::Reinhard => ToneMap::Reinhard,
+            config::ToneMap::AcesFilmic => ToneMap::AcesFilmic,
+        })
+        .with_color_encoding(match config.color_encoding {
+            config::ColorEncoding::Srgb => ColorEncoding::Srgb,
+            config::ColorEncoding::Linear => ColorEncoding::Linear,
+        })
+        .with_alpha_mode(match config.alpha_mode {
+            config::AlphaMode::Straight => AlphaMode::Straight,
+            config::AlphaMode::Premultiplied => AlphaMode::Premultiplied,
+        });
+    if let Some(max_sample_luminance) = config.max_sample_luminance {
+        film = film.with_max_sample_luminance(max_sample_luminance);
+    }
+    if config.depth_image || config.denoise.is_some() {
+        film = film.with_channel(Channel::depth());
+    }
+    if config.normal_image.is_some() || config.denoise.is_some() {
+        film = film.with_channel(Channel::normal());
+    }
+    if config.denoise.is_some() {
+        film = film.with_channel(Channel::albedo());
+    }
+    if config.index_images {
+        film = film
+            .with_channel(Channel::triangle_index())
+            .with_channel(Channel::mesh_index());
+    }
+    film
+}
+
+fn load_filter(filter_config: &config::Filter) -> Box<dyn Filter + Send + Sync> {
+    match *filter_config {
+        config::Filter::Mitchell {
+            x_radius,
+            y_radius,
+            b,
+            c,
+        } => Box::new(MitchellFilter::new(x_radius, y_radius, b, c)),
+        config::Filter::Box { x_radius, y_radius } => Box::new(BoxFilter::new(x_radius, y_radius)),
+        config::Filter::Triangle { x_radius, y_radius } => {
+            Box::new(TriangleFilter::new(x_radius, y_radius))
+        }
+        config::Filter::Gaussian {
+            x_radius,
+            y_radius,
+            alpha,
+        } => Box::new(GaussianFilter::new(x_radius, y_radius, alpha)),
+        config::Filter::LanczosSinc {
+            x_radius,
+            y_radius,
+            tau,
+        } => Box::new(LanczosSincFilter::new(x_radius, y_radius, tau)),
+    }
+}
+
+fn load_sampler(sampler_config: &config::Sampler) -> sampler::Sampler {
     match sampler_config {
-        config::Sampler::StratifiedSampler {
+        config::Sampler::Stratified {
             x_strata_count,
             y_strata_count,
             jitter,
-        } => StratifiedSampler::new(*x_strata_count, *y_strata_count, 5, 0, *jitter),
+        } => sampler::Sampler::Stratified(StratifiedSampler::new(
+            *x_strata_count,
+            *y_strata_count,
+            5,
+            0,
+            *jitter,
+        )),
+        config::Sampler::Halton { samples_per_pixel } => {
+            sampler::Sampler::Halton(HaltonSampler::new(*samples_per_pixel, 0))
+        }
+        config::Sampler::Sobol { samples_per_pixel } => {
+            sampler::Sampler::Sobol(SobolSampler::new(*samples_per_pixel, 5, 0))
+        }
+    }
+}
+
+/// Wrap the sampler in an adaptive sampler. Without an adaptive sampling
+/// configuration, the adaptive sampler takes exactly the samples that the
+/// sampler takes.
+fn load_adaptive_sampler(
+    sampler: sampler::Sampler,
+    adaptive_sampling: Option<config::AdaptiveSampling>,
+) -> AdaptiveSampler<sampler::Sampler> {
+    match adaptive_sampling {
+        Some(config::AdaptiveSampling {
+            max_samples_per_pixel,
+            error_threshold,
+        }) => AdaptiveSampler::new(sampler, max_samples_per_pixel, error_threshold),
+        None => {
+            let samples_per_pixel = sampler.samples_per_pixel();
+            AdaptiveSampler::new(sampler, samples_per_pixel, 0.0)
+        }
+    }
+}
+
+/// Return the render options for the configuration.
+///
+/// When rendering within a time budget, each pass takes one of the sampler's
+/// samples in every pixel, so that a coarse image is ready as soon as possible
+/// and passes can be stopped after any of the samples. Adaptive sampling
+/// decides how many samples to take in a pixel from the samples already taken
+/// there, so its samples aren't split across passes.
+fn load_render_options(
+    config: &Config,
+    samples_per_pixel: usize,
+    deadline: Option<Instant>,
+) -> RenderOptions {
+    let (passes, samples_per_pass) = if deadline.is_some() && config.adaptive_sampling.is_none() {
+        (config.passes * samples_per_pixel, Some(1))
+    } else {
+        (config.passes, None)
+    };
+    RenderOptions {
+        tile_size: config.tile_size,
+        tile_order: match config.tile_order {
+            config::TileOrder::RowMajor => TileOrder::RowMajor,
+            config::TileOrder::Spiral => TileOrder::Spiral,
+            config::TileOrder::Hilbert => TileOrder::Hilbert,
+        },
+        thread_pool: config.thread_pool.clone(),
+        passes,
+        samples_per_pass,
+        on_pass: config.on_snapshot.clone().map(|callback| {
+            Arc::new(move |pass: usize, film: &Film| callback(pass, film.write_image()))
+                as PassCallback
+        }),
+        progress: config.progress.clone().map(|callback| {
+            Arc::new(move |progress: Progress| {
+                callback(progress.completed_tiles, progress.tile_count)
+            }) as ProgressCallback
+        }),
+        deadline,
+        cancellation_token: config.cancellation_token.clone(),
     }
 }
 
@@ -184,7 +548,7 @@ fn orthographic_screen_size(aspect_ratio: f32) -> Vector2<f32> {
 fn origin_to_spherical_position(r: f32, theta: Deg<f32>, phi: Deg<f32>) -> Matrix4<f32> {
     Matrix4::from_angle_z(Rad(0.0) - Rad(FRAC_PI_2) - Rad::from(phi))
         * Matrix4::from_angle_x(Rad(PI) - Rad::from(theta))
-        * Matrix4::from_translation(Vector3::new(0.0, 0.0, -1.0 * r))
+        * Matrix4::from_translation(Vector3::new(0.0, 0.0, -r))
 }
 
 /// Return the maximum distance between any vertex and the origin.
@@ -198,22 +562,21 @@ fn max_distance_from_origin(mesh: &Mesh) -> f32 {
         .sqrt()
 }
 
-/// Crop transparent edges from the image.
-fn crop_to_non_transparent(
-    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
-) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>, Error> {
-    let (crop_bounds_min, crop_bounds_max) =
-        non_transparent_bounds(image).ok_or(Error::ZeroAreaImage)?;
+/// Crop the image to the pixels between the min and max (inclusive) pixels.
+fn crop<P: Pixel + 'static>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    crop_bounds_min: Point2<u32>,
+    crop_bounds_max: Point2<u32>,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
     let crop_bounds_diag = crop_bounds_max - crop_bounds_min;
-    let cropped = imageops::crop_imm(
+    imageops::crop_imm(
         image,
         crop_bounds_min.x,
         crop_bounds_min.y,
         crop_bounds_diag.x + 1,
         crop_bounds_diag.y + 1,
     )
-    .to_image();
-    Ok(cropped)
+    .to_image()
 }
 
 /// Return the min and max (inclusive) pixels of a 2D bounding box around any
@@ -240,3 +603,84 @@ fn non_transparent_bounds(
 
     min_max
 }
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+    use crate::test::square_stl;
+
+    #[test]
+    fn ray_tracer_traces_primary_rays_in_packets() {
+        let config = Config::new_left_handed(32, 32);
+        let rendering = render(square_stl(), &config).unwrap();
+        assert!(rendering.stats.packet_samples > 0);
+    }
+
+    #[test]
+    fn rasterizer_and_adaptive_sampling_trace_primary_rays_one_at_a_time() {
+        let rasterized = render(
+            square_stl(),
+            &Config::new_left_handed(32, 32).rasterizer_backend(),
+        )
+        .unwrap();
+        assert_eq!(rasterized.stats.packet_samples, 0);
+
+        let adaptively_sampled = render(
+            square_stl(),
+            &Config::new_left_handed(32, 32).adaptive_sampling(16, 0.01),
+        )
+        .unwrap();
+        assert_eq!(adaptively_sampled.stats.packet_samples, 0);
+    }
+
+    #[test]
+    fn finishes_the_first_pass_after_the_time_budget_runs_out() {
+        let config = Config::new_left_handed(32, 32).passes(4).crop_transparent();
+        let unbudgeted = render(square_stl(), &config).unwrap();
+        let budgeted = render(square_stl(), &config.time_budget(Duration::ZERO)).unwrap();
+        assert_eq!(budgeted.stats.passes, 1);
+        assert_eq!(budgeted.image.dimensions(), unbudgeted.image.dimensions());
+    }
+
+    #[test]
+    fn time_budget_takes_the_same_samples_over_several_passes() {
+        let config = Config::new_left_handed(32, 32);
+        for config in [
+            config.clone().stratified_sampler(2, 2, true),
+            config.clone().halton_sampler(4),
+            config.sobol_sampler(4),
+        ] {
+            let unbudgeted = render(square_stl(), &config).unwrap();
+            let budgeted =
+                render(square_stl(), &config.time_budget(Duration::from_secs(3600))).unwrap();
+            assert_eq!(budgeted.stats.passes, 4);
+
+            // The samples are added to the film in a different order, which
+            // only changes the pixels by rounding.
+            for (a, b) in unbudgeted.image.pixels().zip(budgeted.image.pixels()) {
+                assert!(a.0.iter().zip(b.0).all(|(a, b)| a.abs_diff(b) <= 1));
+            }
+        }
+    }
+
+    #[test]
+    fn rasterizer_rejects_ray_tracer_options() {
+        let rasterizer = Config::new_left_handed(32, 32).rasterizer_backend();
+        assert!(matches!(
+            render(
+                square_stl(),
+                &rasterizer.clone().adaptive_sampling(16, 0.01)
+            ),
+            Err(Error::UnsupportedByRasterizer("adaptive_sampling"))
+        ));
+        assert!(matches!(
+            render(square_stl(), &rasterizer.max_depth(2)),
+            Err(Error::UnsupportedByRasterizer("max_depth"))
+        ));
+
+        let ray_tracer = Config::new_left_handed(32, 32)
+            .adaptive_sampling(16, 0.01)
+            .max_depth(2);
+        assert!(render(square_stl(), &ray_tracer).is_ok());
+    }
+}
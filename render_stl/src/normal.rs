@@ -0,0 +1,138 @@
+use crate::{hdr, Error};
+use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Rgb, Rgba};
+use std::io::{Seek, Write};
+
+/// A file format for normal images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalFormat {
+    /// PNG, with 16-bit RGBA channels. Each component of a normal is mapped
+    /// from [-1, 1] to [0, 65535], as in a normal map. Pixels that don't cover
+    /// the mesh are transparent.
+    Png16,
+
+    /// Portable float map, with 32-bit float RGB channels that hold the
+    /// normals as they are. Pixels that don't cover the mesh are zero.
+    Pfm,
+}
+
+/// Write a normal image from `Rendering::normal_image` to the writer in the
+/// given format.
+pub fn write_normal_image<W: Write + Seek>(
+    normal_image: &ImageBuffer<Rgb<f32>, Vec<f32>>,
+    w: &mut W,
+    format: NormalFormat,
+) -> Result<(), Error> {
+    match format {
+        NormalFormat::Png16 => {
+            DynamicImage::ImageRgba16(encode(normal_image)).write_to(w, ImageOutputFormat::Png)?
+        }
+        NormalFormat::Pfm => hdr::write_rgb_pfm(normal_image, w)?,
+    }
+    Ok(())
+}
+
+/// Map the components of the normals to [0, 65535], with opaque alpha, and
+/// make the zero normals transparent.
+fn encode(normal_image: &ImageBuffer<Rgb<f32>, Vec<f32>>) -> ImageBuffer<Rgba<u16>, Vec<u16>> {
+    ImageBuffer::from_fn(normal_image.width(), normal_image.height(), |x, y| {
+        let normal = normal_image.get_pixel(x, y).0;
+        if normal == [0.0; 3] {
+            return Rgba([0; 4]);
+        }
+        let [r, g, b] = normal.map(|c| ((0.5 * c + 0.5).clamp(0.0, 1.0) * 65535.0).round() as u16);
+        Rgba([r, g, b, u16::MAX])
+    })
+}
+
+#[cfg(test)]
+mod normal_tests {
+    use crate::test::{assert_near, square_stl, stl};
+    use crate::{render, write_normal_image, Config, NormalFormat};
+    use image::{ImageBuffer, ImageFormat, Rgb};
+    use std::io::Cursor;
+
+    fn render_normals(config: Config) -> ImageBuffer<Rgb<f32>, Vec<f32>> {
+        render(square_stl(), &config).unwrap().normal_image.unwrap()
+    }
+
+    #[test]
+    fn pixels_record_the_normal_of_the_mesh() {
+        // The square faces positive z in the STL file, and the default camera
+        // looks at it along negative z.
+        for (config, normal) in [
+            (
+                Config::new_left_handed(32, 32).normal_image(),
+                [0.0, 0.0, 1.0],
+            ),
+            (
+                Config::new_left_handed(32, 32)
+                    .normal_image()
+                    .rasterizer_backend(),
+                [0.0, 0.0, 1.0],
+            ),
+            (
+                Config::new_left_handed(32, 32).camera_space_normal_image(),
+                [0.0, 0.0, -1.0],
+            ),
+        ] {
+            let normal_image = render_normals(config);
+            assert_near(normal_image.get_pixel(16, 16).0, normal, 1e-5);
+            assert_eq!(normal_image.get_pixel(0, 0).0, [0.0; 3]);
+        }
+    }
+
+    #[test]
+    fn world_space_normals_are_in_the_coordinates_of_the_stl_file() {
+        // A square that's tilted toward negative y, so its normal changes
+        // when the mesh is reflected to make it left-handed.
+        let (a, b, c, d) = (
+            [8.0, 18.0, 5.0],
+            [12.0, 18.0, 5.0],
+            [12.0, 22.0, 7.0],
+            [8.0, 22.0, 7.0],
+        );
+        let normal = [0.0, -1.0 / 5.0f32.sqrt(), 2.0 / 5.0f32.sqrt()];
+        for config in [
+            Config::new_left_handed(32, 32),
+            Config::new_right_handed(32, 32),
+        ] {
+            let rendering = render(stl(&[[a, b, c], [a, c, d]]), &config.normal_image()).unwrap();
+            let normal_image = rendering.normal_image.unwrap();
+            assert_near(normal_image.get_pixel(16, 16).0, normal, 1e-5);
+        }
+    }
+
+    #[test]
+    fn normal_pfm_holds_the_normals_as_they_are() {
+        let normal_image = render_normals(Config::new_left_handed(32, 32).normal_image());
+        let mut pfm = Cursor::new(vec![]);
+        write_normal_image(&normal_image, &mut pfm, NormalFormat::Pfm).unwrap();
+        let pfm = pfm.into_inner();
+
+        let header = b"PF\n32 32\n-1.0\n";
+        assert_eq!(&pfm[..header.len()], header);
+        // Rows are written from bottom to top.
+        let normal_at = |x: usize, y: usize| {
+            let offset = header.len() + 12 * ((31 - y) * 32 + x);
+            [0, 4, 8]
+                .map(|c| f32::from_le_bytes(pfm[offset + c..offset + c + 4].try_into().unwrap()))
+        };
+        assert_eq!(normal_at(16, 16), normal_image.get_pixel(16, 16).0);
+        assert_eq!(normal_at(0, 0), [0.0; 3]);
+    }
+
+    #[test]
+    fn normal_png_maps_components_to_the_full_range() {
+        let normal_image = render_normals(Config::new_left_handed(32, 32).normal_image());
+        let mut png = Cursor::new(vec![]);
+        write_normal_image(&normal_image, &mut png, NormalFormat::Png16).unwrap();
+        let png = image::load_from_memory_with_format(png.get_ref(), ImageFormat::Png)
+            .unwrap()
+            .into_rgba16();
+
+        // Zero components are mapped to the middle of the range, and one is
+        // mapped to the top of it.
+        assert_eq!(png.get_pixel(16, 16).0, [32768, 32768, u16::MAX, u16::MAX]);
+        assert_eq!(png.get_pixel(0, 0).0, [0; 4]);
+    }
+}
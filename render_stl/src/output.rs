@@ -0,0 +1,256 @@
+use crate::config::{AlphaMode, ColorEncoding, Rgb};
+use crate::Error;
+use image::{DynamicImage, ImageBuffer, ImageOutputFormat, Rgb as RgbPixel, RgbImage, Rgba};
+use png::{BitDepth, ColorType, ScaledFloat, SourceChromaticities, SrgbRenderingIntent};
+use std::io::{Seek, Write};
+
+/// A file format for images written by `render_to_writer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// PNG, with 8-bit RGBA channels.
+    #[default]
+    Png,
+
+    /// PNG, with 16-bit RGBA channels.
+    Png16,
+
+    /// JPEG, with the given quality from 1 to 100. The format has no alpha
+    /// channel, so the image is flattened onto the background.
+    Jpeg { quality: u8 },
+
+    /// Lossless WebP, with 8-bit RGBA channels.
+    WebP,
+
+    /// TIFF, with 8-bit RGBA channels.
+    Tiff,
+
+    /// BMP, with 8-bit RGB channels. Few readers support alpha in BMP files,
+    /// so the image is flattened onto the background.
+    Bmp,
+}
+
+/// Write an image with 8-bit channels in the given format, which mustn't be
+/// `OutputFormat::Png16`.
+pub(super) fn write_image<W: Write + Seek>(
+    image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+    format: OutputFormat,
+    color_encoding: ColorEncoding,
+    alpha_mode: AlphaMode,
+    background: Rgb,
+    w: &mut W,
+) -> Result<(), Error> {
+    match format {
+        OutputFormat::Png => write_png(
+            image.width(),
+            image.height(),
+            BitDepth::Eight,
+            image.as_raw(),
+            color_encoding,
+            w,
+        )?,
+        OutputFormat::Png16 => unreachable!("16-bit images are written by write_png_16"),
+        OutputFormat::Jpeg { quality } => {
+            DynamicImage::ImageRgb8(flatten(&image, alpha_mode, background))
+                .write_to(w, ImageOutputFormat::Jpeg(quality.clamp(1, 100)))?
+        }
+        OutputFormat::WebP => {
+            DynamicImage::ImageRgba8(image).write_to(w, ImageOutputFormat::WebP)?
+        }
+        OutputFormat::Tiff => {
+            DynamicImage::ImageRgba8(image).write_to(w, ImageOutputFormat::Tiff)?
+        }
+        OutputFormat::Bmp => DynamicImage::ImageRgb8(flatten(&image, alpha_mode, background))
+            .write_to(w, ImageOutputFormat::Bmp)?,
+    }
+    Ok(())
+}
+
+/// Write an image with 16-bit channels in the PNG format.
+pub(super) fn write_png_16<W: Write>(
+    image: &ImageBuffer<Rgba<u16>, Vec<u16>>,
+    color_encoding: ColorEncoding,
+    w: &mut W,
+) -> Result<(), Error> {
+    // PNG stores 16-bit samples in big-endian order.
+    let data: Vec<u8> = image
+        .as_raw()
+        .iter()
+        .flat_map(|sample| sample.to_be_bytes())
+        .collect();
+    write_png(
+        image.width(),
+        image.height(),
+        BitDepth::Sixteen,
+        &data,
+        color_encoding,
+        w,
+    )
+}
+
+/// Write RGBA image data in the PNG format, with chunks that tell viewers how
+/// its colors are encoded.
+///
+/// sRGB images get an sRGB chunk, along with the gAMA and cHRM chunks that the
+/// PNG specification recommends for decoders that don't support it. Linear
+/// images get a gAMA chunk with a gamma of 1 and the same chromaticities,
+/// since they have the same primaries as sRGB.
+fn write_png<W: Write>(
+    width: u32,
+    height: u32,
+    bit_depth: BitDepth,
+    data: &[u8],
+    color_encoding: ColorEncoding,
+    w: &mut W,
+) -> Result<(), Error> {
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(ColorType::Rgba);
+    encoder.set_depth(bit_depth);
+    match color_encoding {
+        ColorEncoding::Srgb => {
+            encoder.set_source_srgb(SrgbRenderingIntent::Perceptual);
+            encoder.set_source_gamma(ScaledFloat::from_scaled(45455));
+        }
+        ColorEncoding::Linear => encoder.set_source_gamma(ScaledFloat::new(1.0)),
+    }
+    encoder.set_source_chromaticities(SourceChromaticities::new(
+        (0.3127, 0.3290),
+        (0.64, 0.33),
+        (0.30, 0.60),
+        (0.15, 0.06),
+    ));
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Composite the image over an opaque background, for formats without an
+/// alpha channel.
+///
+/// The image is composited in the encoding it's written in, as image editors
+/// do, so the background's components are also in that encoding.
+fn flatten(
+    image: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    alpha_mode: AlphaMode,
+    background: Rgb,
+) -> RgbImage {
+    let background = [background.r, background.g, background.b];
+    ImageBuffer::from_fn(image.width(), image.height(), |x, y| {
+        let Rgba([r, g, b, a]) = *image.get_pixel(x, y);
+        let alpha = a as f32 / 255.0;
+        let mut pixel = [0; 3];
+        for ((output, color), background) in pixel.iter_mut().zip([r, g, b]).zip(background) {
+            let color = color as f32 / 255.0;
+            let color = match alpha_mode {
+                AlphaMode::Straight => alpha * color,
+                AlphaMode::Premultiplied => color,
+            };
+            let composited = color + (1.0 - alpha) * background.clamp(0.0, 1.0);
+            *output = (composited * 255.0).round().min(255.0) as u8;
+        }
+        RgbPixel(pixel)
+    })
+}
+
+#[cfg(test)]
+mod output_tests {
+    use super::flatten;
+    use crate::config::{AlphaMode, Rgb};
+    use crate::test::square_stl;
+    use crate::{render_to_image, render_to_image_16, render_to_writer, Config, OutputFormat};
+    use image::{ColorType, DynamicImage, ImageBuffer, ImageFormat, Rgba};
+    use std::io::Cursor;
+
+    /// Render the square in the format, and return the decoded image along
+    /// with the format that it was detected to be in.
+    fn render_in_format(config: &Config, format: OutputFormat) -> (ImageFormat, DynamicImage) {
+        let mut bytes = Cursor::new(vec![]);
+        render_to_writer(
+            square_stl(),
+            &config.clone().output_format(format),
+            &mut bytes,
+        )
+        .unwrap();
+        let bytes = bytes.into_inner();
+        let detected_format = image::guess_format(&bytes).unwrap();
+        let image = image::load_from_memory_with_format(&bytes, detected_format).unwrap();
+        (detected_format, image)
+    }
+
+    #[test]
+    fn formats_with_alpha_keep_the_image_exactly() {
+        let config = Config::new_left_handed(16, 16);
+        let expected = render_to_image(square_stl(), &config).unwrap();
+        for (format, image_format) in [
+            (OutputFormat::Png, ImageFormat::Png),
+            (OutputFormat::WebP, ImageFormat::WebP),
+            (OutputFormat::Tiff, ImageFormat::Tiff),
+        ] {
+            let (detected_format, image) = render_in_format(&config, format);
+            assert_eq!(detected_format, image_format);
+            assert_eq!(image.color(), ColorType::Rgba8, "{:?}", format);
+            assert_eq!(image.into_rgba8(), expected, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn png_16_keeps_16_bits_per_channel() {
+        let config = Config::new_left_handed(16, 16);
+        let (detected_format, image) = render_in_format(&config, OutputFormat::Png16);
+        assert_eq!(detected_format, ImageFormat::Png);
+        assert_eq!(image.color(), ColorType::Rgba16);
+        assert_eq!(
+            image.into_rgba16(),
+            render_to_image_16(square_stl(), &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn formats_without_alpha_are_flattened_onto_the_background() {
+        let config = Config::new_left_handed(16, 16).background(1.0, 0.0, 0.0);
+        let expected = render_to_image(square_stl(), &config).unwrap();
+        // The corners of the image are transparent, and its center is opaque.
+        assert_eq!(expected.get_pixel(0, 0).0[3], 0);
+        assert_eq!(expected.get_pixel(8, 8).0[3], 255);
+
+        let (detected_format, image) = render_in_format(&config, OutputFormat::Bmp);
+        assert_eq!(detected_format, ImageFormat::Bmp);
+        assert_eq!(image.color(), ColorType::Rgb8);
+        let image = image.into_rgb8();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0]);
+        let [r, g, b, _] = expected.get_pixel(8, 8).0;
+        assert_eq!(image.get_pixel(8, 8).0, [r, g, b]);
+
+        // JPEG is lossy, so its colors are only close to the flattened image's.
+        let (detected_format, image) =
+            render_in_format(&config, OutputFormat::Jpeg { quality: 100 });
+        assert_eq!(detected_format, ImageFormat::Jpeg);
+        assert_eq!(image.color(), ColorType::Rgb8);
+        let [r, g, b] = image.into_rgb8().get_pixel(0, 0).0;
+        assert!(r > 240 && g < 16 && b < 16);
+    }
+
+    #[test]
+    fn jpeg_quality_is_clamped() {
+        let config = Config::new_left_handed(16, 16);
+        let (detected_format, _) = render_in_format(&config, OutputFormat::Jpeg { quality: 0 });
+        assert_eq!(detected_format, ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn flattening_composites_by_alpha_mode() {
+        let image = ImageBuffer::from_pixel(1, 1, Rgba([200, 100, 0, 51]));
+        let background = Rgb {
+            r: 0.0,
+            g: 0.0,
+            b: 1.0,
+        };
+        // With straight alpha, the color is scaled by alpha, which is 0.2,
+        // before the background, scaled by 0.8, is added to it.
+        let straight = flatten(&image, AlphaMode::Straight, background);
+        assert_eq!(straight.get_pixel(0, 0).0, [40, 20, 204]);
+        let premultiplied = flatten(&image, AlphaMode::Premultiplied, background);
+        assert_eq!(premultiplied.get_pixel(0, 0).0, [200, 100, 204]);
+    }
+}
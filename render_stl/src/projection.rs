@@ -0,0 +1,245 @@
+use cgmath::{InnerSpace, Matrix4, Point2, Point3, SquareMatrix, Transform, Vector2, Vector3};
+use ray_tracer::camera::OrthographicCamera;
+
+/// Relates points on a mesh to pixels of the image that it was rendered to,
+/// so that markers can be placed on the image at points on the mesh, or
+/// points on the mesh can be picked from the image.
+///
+/// Points are given either in the coordinates of the STL file, or in world
+/// space, which the mesh is moved into before it's rendered by centering it
+/// at the origin and scaling it to fit in a unit sphere. Pixel coordinates
+/// are continuous: the top left corner of the image is (0, 0), and the
+/// center of the pixel at column x and row y is (x + 0.5, y + 0.5). They
+/// take into account any transparent pixels that were cropped from the
+/// edges of the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    stl_to_world: Matrix4<f32>,
+    world_to_stl: Matrix4<f32>,
+
+    /// The transformation from world space to pixel coordinates in the
+    /// uncropped image, with z pointing in the camera's viewing direction.
+    world_to_raster: Matrix4<f32>,
+    raster_to_world: Matrix4<f32>,
+
+    /// The position of the cropped image's top left pixel in the uncropped
+    /// image.
+    crop_offset: Vector2<f32>,
+}
+
+/// A ray in world space, which starts at the camera's near clipping plane
+/// and has a unit length direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: [f32; 3],
+    pub direction: [f32; 3],
+}
+
+impl Ray {
+    /// Return the point that is `t` along the ray.
+    pub fn at(&self, t: f32) -> [f32; 3] {
+        (Point3::from(self.origin) + t * Vector3::from(self.direction)).into()
+    }
+}
+
+impl Projection {
+    /// Return the projection, or `None` if either transformation can't be
+    /// inverted.
+    ///
+    /// * stl_to_world - The transformation that moved the mesh from the
+    ///   coordinates of the STL file into world space.
+    /// * camera - The camera that the mesh was rendered with.
+    pub(crate) fn new(stl_to_world: Matrix4<f32>, camera: &OrthographicCamera) -> Option<Self> {
+        let world_to_raster = camera.world_to_raster();
+        Some(Self {
+            stl_to_world,
+            world_to_stl: invert(&stl_to_world)?,
+            world_to_raster,
+            raster_to_world: invert(&world_to_raster)?,
+            crop_offset: Vector2::new(0.0, 0.0),
+        })
+    }
+
+    /// Return the projection onto the image after it's cropped, given the
+    /// min pixel of the crop bounds.
+    pub(crate) fn cropped(self, crop_bounds_min: Point2<u32>) -> Self {
+        Self {
+            crop_offset: Vector2::new(crop_bounds_min.x as f32, crop_bounds_min.y as f32),
+            ..self
+        }
+    }
+
+    /// Return the ray in world space that the camera traces through the given
+    /// pixel coordinates. Rays through the centers of pixels hit the surfaces
+    /// that are visible in them.
+    pub fn pixel_to_ray(&self, pixel: [f32; 2]) -> Ray {
+        let raster = Point2::from(pixel) + self.crop_offset;
+        let origin = self
+            .raster_to_world
+            .transform_point(Point3::new(raster.x, raster.y, 0.0));
+        let direction = self
+            .raster_to_world
+            .transform_vector(Vector3::new(0.0, 0.0, 1.0))
+            .normalize();
+        Ray {
+            origin: origin.into(),
+            direction: direction.into(),
+        }
+    }
+
+    /// Return the pixel coordinates that the given point in world space is
+    /// projected to. Points that project outside of the image have pixel
+    /// coordinates outside of its bounds.
+    pub fn world_to_pixel(&self, point: [f32; 3]) -> [f32; 2] {
+        let raster = self.world_to_raster.transform_point(Point3::from(point));
+        [raster.x - self.crop_offset.x, raster.y - self.crop_offset.y]
+    }
+
+    /// Return the pixel coordinates that the given point in the coordinates of
+    /// the STL file is projected to. Points that project outside of the image
+    /// have pixel coordinates outside of its bounds.
+    pub fn stl_to_pixel(&self, point: [f32; 3]) -> [f32; 2] {
+        self.world_to_pixel(self.stl_to_world(point))
+    }
+
+    /// Return the point in world space at the given point in the coordinates
+    /// of the STL file.
+    pub fn stl_to_world(&self, point: [f32; 3]) -> [f32; 3] {
+        self.stl_to_world
+            .transform_point(Point3::from(point))
+            .into()
+    }
+
+    /// Return the point in the coordinates of the STL file at the given point
+    /// in world space.
+    pub fn world_to_stl(&self, point: [f32; 3]) -> [f32; 3] {
+        self.world_to_stl
+            .transform_point(Point3::from(point))
+            .into()
+    }
+}
+
+/// Return the inverse of the transformation, or `None` if it's singular or
+/// isn't finite. For example, the transformation that scales a mesh whose
+/// vertices are all at the same point to fit in a unit sphere scales it by
+/// infinity.
+fn invert(transform: &Matrix4<f32>) -> Option<Matrix4<f32>> {
+    let is_finite = |m: &Matrix4<f32>| {
+        let entries: &[f32; 16] = m.as_ref();
+        entries.iter().all(|entry| entry.is_finite())
+    };
+    transform
+        .invert()
+        .filter(|inverse| is_finite(transform) && is_finite(inverse))
+}
+
+#[cfg(test)]
+mod projection_tests {
+    use crate::test::{assert_near, square_stl, stl, SQUARE_CORNERS};
+    use crate::{non_transparent_bounds, render, Config, Rendering};
+    use cgmath::{InnerSpace, Point3};
+
+    /// A point inside the square, and one outside of it that's still in the
+    /// image.
+    const INSIDE: [f32; 3] = [9.0, 21.5, 5.0];
+    const OUTSIDE: [f32; 3] = [12.5, 20.0, 5.0];
+
+    fn render_square(config: Config) -> Rendering {
+        render(square_stl(), &config.depth_image()).unwrap()
+    }
+
+    /// Return the pixel that contains the given pixel coordinates.
+    fn containing_pixel([x, y]: [f32; 2]) -> (u32, u32) {
+        (x.floor() as u32, y.floor() as u32)
+    }
+
+    #[test]
+    fn points_round_trip_through_world_space() {
+        for config in [
+            Config::new_left_handed(64, 64),
+            Config::new_right_handed(64, 64),
+        ] {
+            let projection = render_square(config).projection.unwrap();
+            for point in SQUARE_CORNERS.into_iter().chain([INSIDE, OUTSIDE]) {
+                let world = projection.stl_to_world(point);
+                assert_near(projection.world_to_stl(world), point, 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn rays_through_projected_points_pass_through_them() {
+        let projection = render_square(Config::new_left_handed(64, 64))
+            .projection
+            .unwrap();
+        for point in SQUARE_CORNERS.into_iter().chain([INSIDE, OUTSIDE]) {
+            let ray = projection.pixel_to_ray(projection.stl_to_pixel(point));
+            let world = Point3::from(projection.stl_to_world(point));
+            let t = (world - Point3::from(ray.origin)).dot(ray.direction.into());
+            assert!(t > 0.0);
+            assert_near(ray.at(t), world.into(), 1e-5);
+        }
+    }
+
+    #[test]
+    fn projected_points_are_where_the_mesh_was_rendered() {
+        let rendering = render_square(Config::new_left_handed(64, 64));
+        let projection = rendering.projection.unwrap();
+
+        let (x, y) = containing_pixel(projection.stl_to_pixel(INSIDE));
+        assert_eq!(rendering.image.get_pixel(x, y).0[3], 255);
+        // The hit point is on the ray through the center of the pixel, so it's
+        // at most half of a pixel's diagonal away from the point.
+        let half_pixel_diagonal = 2.0 * 2.0f32.sqrt() / 64.0 / 2.0;
+        let hit_point = projection.world_to_stl(rendering.hit_point(x, y).unwrap());
+        let stl_units_per_world_unit = 2.0 * 2.0f32.sqrt();
+        assert_near(
+            hit_point,
+            INSIDE,
+            half_pixel_diagonal * stl_units_per_world_unit,
+        );
+
+        let (x, y) = containing_pixel(projection.stl_to_pixel(OUTSIDE));
+        assert!(x < 64 && y < 64);
+        assert_eq!(rendering.image.get_pixel(x, y).0[3], 0);
+        assert_eq!(rendering.hit_point(x, y), None);
+    }
+
+    #[test]
+    fn cropping_offsets_pixel_coordinates() {
+        let uncropped = render_square(Config::new_left_handed(64, 64));
+        let cropped = render_square(Config::new_left_handed(64, 64).crop_transparent());
+        let (crop_bounds_min, _) = non_transparent_bounds(&uncropped.image).unwrap();
+        assert!(crop_bounds_min.x > 0 && crop_bounds_min.y > 0);
+
+        let uncropped_projection = uncropped.projection.unwrap();
+        let cropped_projection = cropped.projection.unwrap();
+        let uncropped_pixel = uncropped_projection.stl_to_pixel(INSIDE);
+        let cropped_pixel = cropped_projection.stl_to_pixel(INSIDE);
+        assert_near(
+            cropped_pixel,
+            [
+                uncropped_pixel[0] - crop_bounds_min.x as f32,
+                uncropped_pixel[1] - crop_bounds_min.y as f32,
+            ],
+            1e-4,
+        );
+        assert_eq!(
+            cropped_projection.pixel_to_ray(cropped_pixel),
+            uncropped_projection.pixel_to_ray(uncropped_pixel)
+        );
+
+        let (x, y) = containing_pixel(cropped_pixel);
+        assert_eq!(cropped.hit_point(x, y), {
+            let (x, y) = containing_pixel(uncropped_pixel);
+            uncropped.hit_point(x, y)
+        });
+    }
+
+    #[test]
+    fn meshes_at_a_single_point_have_no_projection() {
+        let point = [1.0, 2.0, 3.0];
+        let rendering = render(stl(&[[point; 3]]), &Config::new_left_handed(8, 8)).unwrap();
+        assert!(rendering.projection.is_none());
+    }
+}
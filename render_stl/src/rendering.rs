@@ -0,0 +1,104 @@
+use crate::projection::Projection;
+use image::{ImageBuffer, Luma, Rgb, Rgba};
+use std::time::Duration;
+
+/// The result of rendering a mesh.
+#[derive(Debug, Clone)]
+pub struct Rendering {
+    /// The rendered image.
+    pub image: ImageBuffer<Rgba<u8>, Vec<u8>>,
+
+    /// An image of the same size as `image` that marks the pixels in which
+    /// samples with NaN or infinite radiance were taken in opaque magenta, if
+    /// the configuration asked for one.
+    pub non_finite_image: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+
+    /// An image of the same size as `image` with the depth of the mesh in each
+    /// pixel, if the configuration asked for one. Pixels that don't cover the
+    /// mesh are infinitely deep. `write_depth_image` writes it to a file.
+    pub depth_image: Option<ImageBuffer<Luma<f32>, Vec<f32>>>,
+
+    /// An image of the same size as `image` with the unit length normal of the
+    /// mesh in each pixel, if the configuration asked for one. Pixels that
+    /// don't cover the mesh are zero. `write_normal_image` writes it to a
+    /// file.
+    pub normal_image: Option<ImageBuffer<Rgb<f32>, Vec<f32>>>,
+
+    /// An image of the same size as `image` with the index of the triangle in
+    /// the STL file that's visible in each pixel, if the configuration asked
+    /// for one. Pixels that don't cover the mesh are `NO_INDEX`.
+    /// `write_index_buffer` writes it to a file.
+    pub triangle_index_image: Option<ImageBuffer<Luma<u32>, Vec<u32>>>,
+
+    /// An image of the same size as `image` with the index of the mesh that's
+    /// visible in each pixel, if the configuration asked for one. An STL file
+    /// holds a single mesh, so this is zero in pixels that cover the mesh and
+    /// `NO_INDEX` elsewhere.
+    pub mesh_index_image: Option<ImageBuffer<Luma<u32>, Vec<u32>>>,
+
+    /// Relates points on the mesh to pixels of `image`. This is `None` if the
+    /// mesh couldn't be moved into world space in an invertible way, which
+    /// happens when all of its vertices are at the same point.
+    pub projection: Option<Projection>,
+
+    /// Measurements taken while rendering the image.
+    pub stats: RenderStats,
+}
+
+impl Rendering {
+    /// Return the point in world space on the mesh that's visible in the pixel
+    /// at column x and row y, or `None` if the pixel doesn't cover the mesh.
+    /// `Projection::world_to_stl` converts it to the coordinates of the STL
+    /// file.
+    ///
+    /// The point is on the ray through the center of the pixel, at the depth
+    /// of the nearest surface in the pixel, so this is always `None` unless
+    /// the configuration asked for a depth image, and there's a projection.
+    pub fn hit_point(&self, x: u32, y: u32) -> Option<[f32; 3]> {
+        let projection = self.projection.as_ref()?;
+        let depth_image = self.depth_image.as_ref()?;
+        if x >= depth_image.width() || y >= depth_image.height() {
+            return None;
+        }
+        let depth = depth_image.get_pixel(x, y).0[0];
+        depth.is_finite().then(|| {
+            projection
+                .pixel_to_ray([x as f32 + 0.5, y as f32 + 0.5])
+                .at(depth)
+        })
+    }
+}
+
+/// Measurements taken while rendering a mesh.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStats {
+    /// Time spent building the bounding volume hierarchy around the mesh's
+    /// triangles. This is zero when the mesh is rasterized.
+    pub bvh_build_time: Duration,
+
+    /// Time spent tracing rays, or rasterizing triangles, and filtering the
+    /// resulting radiance onto the film.
+    pub trace_time: Duration,
+
+    /// Time spent removing noise from the image. This is zero unless the
+    /// configuration asked for it to be denoised.
+    pub denoise_time: Duration,
+
+    /// The number of complete passes over the image that were rendered. This
+    /// is fewer than the configured number of passes if the time budget ran
+    /// out first, but the first pass is always finished.
+    pub passes: usize,
+
+    /// The average number of samples taken in each pixel of the image, over
+    /// every pass. This varies from pixel to pixel with adaptive sampling.
+    pub mean_samples_per_pixel: f32,
+
+    /// The number of samples whose radiance had a NaN or infinite component.
+    /// These samples are dropped, so they leave no trace in the image.
+    pub non_finite_samples: u64,
+
+    /// The number of samples whose primary rays were traced through the mesh
+    /// in coherent packets. The rest were traced one at a time, as they are
+    /// when the mesh is rasterized or sampled adaptively.
+    pub packet_samples: u64,
+}
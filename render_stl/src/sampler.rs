@@ -0,0 +1,69 @@
+use cgmath::Point2;
+use ray_tracer::sampler::{HaltonSampler, IncrementalSampler, SobolSampler, StratifiedSampler};
+
+/// One of the samplers that can be selected in the configuration. The
+/// renderers are generic over the sampler, so this lets the sampler be chosen
+/// at runtime.
+pub(super) enum Sampler {
+    Stratified(StratifiedSampler),
+    Halton(HaltonSampler),
+    Sobol(SobolSampler),
+}
+
+impl IncrementalSampler for Sampler {
+    fn clone_with_seed(&self, seed: u64) -> Self {
+        match self {
+            Self::Stratified(sampler) => Self::Stratified(sampler.clone_with_seed(seed)),
+            Self::Halton(sampler) => Self::Halton(sampler.clone_with_seed(seed)),
+            Self::Sobol(sampler) => Self::Sobol(sampler.clone_with_seed(seed)),
+        }
+    }
+
+    fn samples_per_pixel(&self) -> usize {
+        match self {
+            Self::Stratified(sampler) => sampler.samples_per_pixel(),
+            Self::Halton(sampler) => sampler.samples_per_pixel(),
+            Self::Sobol(sampler) => sampler.samples_per_pixel(),
+        }
+    }
+
+    fn start_pixel(&mut self, pixel: Point2<i32>) {
+        match self {
+            Self::Stratified(sampler) => sampler.start_pixel(pixel),
+            Self::Halton(sampler) => sampler.start_pixel(pixel),
+            Self::Sobol(sampler) => sampler.start_pixel(pixel),
+        }
+    }
+
+    fn start_pixel_sample(&mut self, pixel: Point2<i32>, sample_index: usize) {
+        match self {
+            Self::Stratified(sampler) => sampler.start_pixel_sample(pixel, sample_index),
+            Self::Halton(sampler) => sampler.start_pixel_sample(pixel, sample_index),
+            Self::Sobol(sampler) => sampler.start_pixel_sample(pixel, sample_index),
+        }
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        match self {
+            Self::Stratified(sampler) => sampler.get_1d(),
+            Self::Halton(sampler) => sampler.get_1d(),
+            Self::Sobol(sampler) => sampler.get_1d(),
+        }
+    }
+
+    fn get_2d(&mut self) -> Point2<f32> {
+        match self {
+            Self::Stratified(sampler) => sampler.get_2d(),
+            Self::Halton(sampler) => sampler.get_2d(),
+            Self::Sobol(sampler) => sampler.get_2d(),
+        }
+    }
+
+    fn start_next_sample(&mut self) -> bool {
+        match self {
+            Self::Stratified(sampler) => sampler.start_next_sample(),
+            Self::Halton(sampler) => sampler.start_next_sample(),
+            Self::Sobol(sampler) => sampler.start_next_sample(),
+        }
+    }
+}
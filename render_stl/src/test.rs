@@ -0,0 +1,52 @@
+use std::io::Cursor;
+
+/// Return a binary STL file of the triangles, each given by the positions of
+/// its three vertices.
+pub fn stl(triangles: &[[[f32; 3]; 3]]) -> Cursor<Vec<u8>> {
+    let mut bytes = vec![0; 80];
+    bytes.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+    for triangle in triangles {
+        // The normal isn't used, since it's recomputed from the vertices.
+        bytes.extend_from_slice(&[0; 12]);
+        for coordinate in triangle.iter().flatten() {
+            bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0; 2]);
+    }
+    Cursor::new(bytes)
+}
+
+/// The corners of a square with sides 4 units long, parallel to the xy plane
+/// and centered at (10, 20, 5), in counterclockwise order when viewed from
+/// positive z.
+pub const SQUARE_CORNERS: [[f32; 3]; 4] = [
+    [8.0, 18.0, 5.0],
+    [12.0, 18.0, 5.0],
+    [12.0, 22.0, 5.0],
+    [8.0, 22.0, 5.0],
+];
+
+/// Return an STL file of the square with `SQUARE_CORNERS`, split into two
+/// triangles along the diagonal from its first corner to its third corner.
+/// Both triangles face positive z, which is where the default camera looks
+/// at the mesh from.
+pub fn square_stl() -> Cursor<Vec<u8>> {
+    let [a, b, c, d] = SQUARE_CORNERS;
+    stl(&[[a, b, c], [a, c, d]])
+}
+
+/// Assert that each component of `actual` is within `tolerance` of the
+/// corresponding component of `expected`.
+#[track_caller]
+pub fn assert_near<const N: usize>(actual: [f32; N], expected: [f32; N], tolerance: f32) {
+    assert!(
+        actual
+            .iter()
+            .zip(&expected)
+            .all(|(a, e)| (a - e).abs() <= tolerance),
+        "{:?} is not within {} of {:?}",
+        actual,
+        tolerance,
+        expected
+    );
+}
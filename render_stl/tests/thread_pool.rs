@@ -0,0 +1,40 @@
+//! Rayon's global thread pool is shared by every test in a test binary, so
+//! checking that rendering leaves it alone needs a binary of its own.
+
+use rayon::ThreadPoolBuilder;
+use render_stl::Config;
+use std::io::Cursor;
+use std::sync::Arc;
+
+/// Return a binary STL file of a square made of two triangles.
+fn square_stl() -> Cursor<Vec<u8>> {
+    let triangles = [
+        [[8.0f32, 18.0, 5.0], [12.0, 18.0, 5.0], [12.0, 22.0, 5.0]],
+        [[8.0, 18.0, 5.0], [12.0, 22.0, 5.0], [8.0, 22.0, 5.0]],
+    ];
+    let mut bytes = vec![0; 80];
+    bytes.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+    for triangle in triangles {
+        bytes.extend_from_slice(&[0; 12]);
+        for coordinate in triangle.iter().flatten() {
+            bytes.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0; 2]);
+    }
+    Cursor::new(bytes)
+}
+
+#[test]
+fn renders_entirely_in_the_configured_thread_pool() {
+    let thread_pool = Arc::new(ThreadPoolBuilder::new().num_threads(2).build().unwrap());
+    for config in [
+        Config::new_left_handed(32, 32),
+        Config::new_left_handed(32, 32).rasterizer_backend(),
+    ] {
+        render_stl::render(square_stl(), &config.thread_pool(thread_pool.clone())).unwrap();
+    }
+
+    // The global thread pool is only started the first time that work is run
+    // in it, so it can still be configured if nothing was run there.
+    assert!(ThreadPoolBuilder::new().build_global().is_ok());
+}